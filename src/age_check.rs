@@ -0,0 +1,61 @@
+// Age-restricted items (alcohol, tobacco, ...) need an ID check before sale.
+//
+// Like `Vault::unlock` in topics/closure_8, the cart doesn't know HOW the
+// check is done. It hands the required age to a `procedure` closure (a
+// cashier looking at an ID, a scanner, a hardcoded answer in a test) and
+// only cares whether it says yes. If it says no, the restricted lines are
+// taken out of the cart and handed back; checkout refuses any restricted
+// line that was never verified at all.
+
+use crate::{ShoppingCart, SupermarketItem};
+
+#[derive(Debug)]
+pub enum AgeError {
+    // The ID check failed; these lines were removed from the cart.
+    Refused(Vec<SupermarketItem>),
+    // Checkout was attempted with this restricted line never verified.
+    NotVerified(String),
+}
+
+impl ShoppingCart {
+    // The highest minimum age of anything in the cart, if any item has one.
+    pub fn required_age(&self) -> Option<u32> {
+        self.item.iter().filter_map(|item| item.min_age).max()
+    }
+
+    pub fn verify_age<F>(&mut self, procedure: F) -> Result<(), AgeError>
+    where
+        F: FnOnce(u32) -> bool,
+    {
+        let required = match self.required_age() {
+            Some(required) => required,
+            None => return Ok(()),
+        };
+
+        if procedure(required) {
+            self.verified_age = Some(required);
+            return Ok(());
+        }
+
+        let mut refused = Vec::new();
+        let mut kept = Vec::new();
+        for item in self.item.drain(..) {
+            if item.min_age.is_some() {
+                refused.push(item);
+            } else {
+                kept.push(item);
+            }
+        }
+        self.item = kept;
+        Err(AgeError::Refused(refused))
+    }
+
+    // The first line whose age restriction hasn't been covered by a
+    // successful `verify_age`.
+    pub fn unverified_line(&self) -> Option<&SupermarketItem> {
+        let verified = self.verified_age.unwrap_or(0);
+        self.item
+            .iter()
+            .find(|item| item.min_age.is_some_and(|age| age > verified))
+    }
+}
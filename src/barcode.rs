@@ -0,0 +1,237 @@
+// Barcode parsing: EAN-13, EAN-8, UPC-A and GS1 variable-measure codes.
+//
+// Every code ends in a mod-10 check digit, computed by weighting the other
+// digits 3, 1, 3, 1, ... starting from the right.
+//
+// Variable-measure codes are EAN-13s starting with 2 that a scale in store
+// prints for loose goods. This store uses the layout
+//
+//   2 P IIIII VVVVV C
+//
+// where IIIII is the item code, VVVVV the embedded value and C the check
+// digit. With P from 0 to 4 the value is the price in cents; with P from 5
+// to 9 it's the weight in grams.
+
+use crate::ShoppingCart;
+use crate::catalog::Catalog;
+use crate::error::CartError;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Barcode {
+    Ean13(String),
+    Ean8(String),
+    UpcA(String),
+    VariableMeasure { item_code: String, value: Embedded },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Embedded {
+    Price(f64),
+    WeightKg(f64),
+}
+
+#[derive(Debug, PartialEq)]
+pub enum BarcodeError {
+    NotDigits,
+    BadLength(usize),
+    BadCheckDigit { expected: u32, found: u32 },
+    Unknown(String),
+}
+
+fn check_digit(digits: &[u32]) -> u32 {
+    let sum: u32 = digits
+        .iter()
+        .rev()
+        .enumerate()
+        .map(|(index, digit)| if index % 2 == 0 { digit * 3 } else { *digit })
+        .sum();
+    (10 - sum % 10) % 10
+}
+
+fn number(digits: &[u32]) -> u32 {
+    digits.iter().fold(0, |total, digit| total * 10 + digit)
+}
+
+impl Barcode {
+    pub fn parse(raw: &str) -> Result<Barcode, BarcodeError> {
+        let code: String = raw.chars().filter(|ch| !ch.is_whitespace()).collect();
+        let digits: Vec<u32> = code
+            .chars()
+            .map(|ch| ch.to_digit(10))
+            .collect::<Option<_>>()
+            .ok_or(BarcodeError::NotDigits)?;
+        if !matches!(digits.len(), 8 | 12 | 13) {
+            return Err(BarcodeError::BadLength(digits.len()));
+        }
+
+        let (body, found) = digits.split_at(digits.len() - 1);
+        let expected = check_digit(body);
+        if found[0] != expected {
+            return Err(BarcodeError::BadCheckDigit {
+                expected,
+                found: found[0],
+            });
+        }
+
+        Ok(match digits.len() {
+            8 => Barcode::Ean8(code),
+            12 => Barcode::UpcA(code),
+            _ if digits[0] == 2 => {
+                let value = number(&digits[7..12]) as f64;
+                Barcode::VariableMeasure {
+                    item_code: code[2..7].to_string(),
+                    value: if digits[1] <= 4 {
+                        Embedded::Price(value / 100.0)
+                    } else {
+                        Embedded::WeightKg(value / 1000.0)
+                    },
+                }
+            }
+            _ => Barcode::Ean13(code),
+        })
+    }
+
+    // The key a product is registered under in the catalog. A UPC-A code is
+    // the same product as the EAN-13 with a leading zero, so both map to
+    // the 13 digit form; variable-measure codes map to their item code.
+    pub fn lookup_key(&self) -> String {
+        match self {
+            Barcode::Ean13(code) | Barcode::Ean8(code) => code.clone(),
+            Barcode::UpcA(code) => format!("0{}", code),
+            Barcode::VariableMeasure { item_code, .. } => format!("2:{}", item_code),
+        }
+    }
+
+    fn embedded(&self) -> Option<Embedded> {
+        match self {
+            Barcode::VariableMeasure { value, .. } => Some(*value),
+            _ => None,
+        }
+    }
+}
+
+impl ShoppingCart {
+    // Adds one of the catalog product `sku` to the cart.
+    pub fn add_by_sku(&mut self, sku: &str, catalog: &Catalog) -> Result<(), CartError> {
+        let entry = catalog
+            .get(sku)
+            .ok_or_else(|| CartError::UnknownSku(sku.to_string()))?;
+        self.item.push(entry.item.clone());
+        Ok(())
+    }
+
+    // Adds the scanned product. For a variable-measure code the embedded
+    // price replaces the catalog price; for an embedded weight, the catalog
+    // price is taken to be per kg and the line is priced by weight.
+    pub fn scan(&mut self, raw: &str, catalog: &Catalog) -> Result<(), CartError> {
+        let barcode = Barcode::parse(raw)?;
+        let key = barcode.lookup_key();
+        let sku = catalog
+            .sku_for_barcode(&key)
+            .ok_or(BarcodeError::Unknown(key.clone()))?;
+        let mut item = catalog
+            .get(sku)
+            .ok_or_else(|| CartError::UnknownSku(sku.to_string()))?
+            .item
+            .clone();
+
+        match barcode.embedded() {
+            Some(Embedded::Price(price)) => item.price = price,
+            Some(Embedded::WeightKg(kg)) => item.price_by_weight(kg),
+            None => {}
+        }
+
+        item.validate()?;
+        self.item.push(item);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SupermarketItem;
+
+    #[test]
+    fn check_digits_are_verified_for_every_format() {
+        assert_eq!(
+            Barcode::parse("5012345678900"),
+            Ok(Barcode::Ean13("5012345678900".to_string()))
+        );
+        assert_eq!(
+            Barcode::parse("036000 291452"),
+            Ok(Barcode::UpcA("036000291452".to_string()))
+        );
+        assert_eq!(
+            Barcode::parse("96385074"),
+            Ok(Barcode::Ean8("96385074".to_string()))
+        );
+
+        assert_eq!(
+            Barcode::parse("5012345678901"),
+            Err(BarcodeError::BadCheckDigit {
+                expected: 0,
+                found: 1
+            })
+        );
+        assert_eq!(
+            Barcode::parse("036000291453"),
+            Err(BarcodeError::BadCheckDigit {
+                expected: 2,
+                found: 3
+            })
+        );
+        assert_eq!(
+            Barcode::parse("96385075"),
+            Err(BarcodeError::BadCheckDigit {
+                expected: 4,
+                found: 5
+            })
+        );
+        assert_eq!(Barcode::parse("12345"), Err(BarcodeError::BadLength(5)));
+        assert_eq!(Barcode::parse("9638507X"), Err(BarcodeError::NotDigits));
+    }
+
+    #[test]
+    fn upc_a_and_its_ean_13_form_are_one_product() {
+        let upc = Barcode::parse("036000291452").unwrap();
+        let ean = Barcode::parse("0036000291452").unwrap();
+        assert_eq!(upc.lookup_key(), ean.lookup_key());
+    }
+
+    #[test]
+    fn variable_measure_codes_carry_a_price_or_a_weight() {
+        assert_eq!(
+            Barcode::parse("2012345004993"),
+            Ok(Barcode::VariableMeasure {
+                item_code: "12345".to_string(),
+                value: Embedded::Price(4.99),
+            })
+        );
+        assert_eq!(
+            Barcode::parse("2712340012503"),
+            Ok(Barcode::VariableMeasure {
+                item_code: "12340".to_string(),
+                value: Embedded::WeightKg(1.25),
+            })
+        );
+    }
+
+    #[test]
+    fn a_weighed_item_is_priced_by_its_embedded_weight() {
+        let mut catalog = Catalog::new();
+        catalog
+            .add("1006", SupermarketItem::new("LOOSE APPLES", 2.40).unwrap())
+            .unwrap();
+        catalog.add_barcode("2712340012503", "1006").unwrap();
+        let mut cart = ShoppingCart::new(Vec::new()).unwrap();
+
+        cart.scan("2712340012503", &catalog).unwrap();
+        assert_eq!(cart.item[0].price, 3.00);
+        assert_eq!(cart.item[0].weight_kg, 1.25);
+        assert!(matches!(
+            cart.scan("5012345678900", &catalog),
+            Err(CartError::Barcode(BarcodeError::Unknown(_)))
+        ));
+    }
+}
@@ -0,0 +1,188 @@
+// Fluent builders for items and carts, so fixtures don't have to spell out
+// every field of a SupermarketItem:
+//
+//   ShoppingCart::builder()
+//       .item("APPLE", 3.99).qty(2).category("produce")
+//       .item("BANANA", 2.99)
+//       .build()?
+//
+// Anything that isn't set keeps the same default as SupermarketItem::new.
+// Nothing is validated until `build`, which checks every line.
+
+use crate::error::{CartError, ItemError};
+use crate::nutrition::{Allergen, DietTag, Nutrition};
+use crate::unit_price::{PackSize, Unit};
+use crate::{ShoppingCart, SupermarketItem};
+
+pub struct ItemBuilder {
+    item: SupermarketItem,
+}
+
+pub struct CartBuilder {
+    lines: Vec<SupermarketItem>,
+}
+
+// A cart builder with a current line: the item setters apply to the line
+// most recently started with `item`.
+pub struct CartLineBuilder {
+    cart: CartBuilder,
+    line: ItemBuilder,
+}
+
+impl SupermarketItem {
+    pub fn builder(name: &str, price: f64) -> ItemBuilder {
+        ItemBuilder {
+            item: SupermarketItem {
+                name: name.to_string(),
+                price,
+                quantity: 1,
+                weight_kg: 0.0,
+                dimensions_cm: [0.0; 3],
+                min_age: None,
+                category: None,
+                aisle: None,
+                pack: None,
+                nutrition: None,
+                allergens: Vec::new(),
+                diet: Vec::new(),
+                price_cut: None,
+                multi_buy: None,
+            },
+        }
+    }
+}
+
+impl ItemBuilder {
+    pub fn qty(mut self, quantity: u32) -> ItemBuilder {
+        self.item.quantity = quantity;
+        self
+    }
+
+    pub fn category(mut self, category: &str) -> ItemBuilder {
+        self.item.category = Some(category.to_string());
+        self
+    }
+
+    pub fn aisle(mut self, aisle: u32) -> ItemBuilder {
+        self.item.aisle = Some(aisle);
+        self
+    }
+
+    pub fn weight(mut self, kg: f64) -> ItemBuilder {
+        self.item.weight_kg = kg;
+        self
+    }
+
+    pub fn dimensions(mut self, cm: [f64; 3]) -> ItemBuilder {
+        self.item.dimensions_cm = cm;
+        self
+    }
+
+    pub fn min_age(mut self, age: u32) -> ItemBuilder {
+        self.item.min_age = Some(age);
+        self
+    }
+
+    pub fn pack(mut self, amount: f64, unit: Unit) -> ItemBuilder {
+        self.item.pack = Some(PackSize::new(amount, unit));
+        self
+    }
+
+    pub fn nutrition(mut self, per_100g: Nutrition) -> ItemBuilder {
+        self.item.nutrition = Some(per_100g);
+        self
+    }
+
+    pub fn allergen(mut self, allergen: Allergen) -> ItemBuilder {
+        self.item.allergens.push(allergen);
+        self
+    }
+
+    pub fn diet(mut self, tag: DietTag) -> ItemBuilder {
+        self.item.diet.push(tag);
+        self
+    }
+
+    pub fn build(self) -> Result<SupermarketItem, ItemError> {
+        self.item.validate()?;
+        Ok(self.item)
+    }
+}
+
+impl ShoppingCart {
+    pub fn builder() -> CartBuilder {
+        CartBuilder { lines: Vec::new() }
+    }
+}
+
+impl CartBuilder {
+    pub fn item(self, name: &str, price: f64) -> CartLineBuilder {
+        CartLineBuilder {
+            cart: self,
+            line: SupermarketItem::builder(name, price),
+        }
+    }
+}
+
+impl CartLineBuilder {
+    // Finishes the current line and starts the next one.
+    pub fn item(mut self, name: &str, price: f64) -> CartLineBuilder {
+        self.cart.lines.push(self.line.item);
+        self.cart.item(name, price)
+    }
+
+    pub fn qty(self, quantity: u32) -> CartLineBuilder {
+        self.with_line(|line| line.qty(quantity))
+    }
+
+    pub fn category(self, category: &str) -> CartLineBuilder {
+        self.with_line(|line| line.category(category))
+    }
+
+    pub fn aisle(self, aisle: u32) -> CartLineBuilder {
+        self.with_line(|line| line.aisle(aisle))
+    }
+
+    pub fn weight(self, kg: f64) -> CartLineBuilder {
+        self.with_line(|line| line.weight(kg))
+    }
+
+    pub fn dimensions(self, cm: [f64; 3]) -> CartLineBuilder {
+        self.with_line(|line| line.dimensions(cm))
+    }
+
+    pub fn min_age(self, age: u32) -> CartLineBuilder {
+        self.with_line(|line| line.min_age(age))
+    }
+
+    pub fn pack(self, amount: f64, unit: Unit) -> CartLineBuilder {
+        self.with_line(|line| line.pack(amount, unit))
+    }
+
+    pub fn nutrition(self, per_100g: Nutrition) -> CartLineBuilder {
+        self.with_line(|line| line.nutrition(per_100g))
+    }
+
+    pub fn allergen(self, allergen: Allergen) -> CartLineBuilder {
+        self.with_line(|line| line.allergen(allergen))
+    }
+
+    pub fn diet(self, tag: DietTag) -> CartLineBuilder {
+        self.with_line(|line| line.diet(tag))
+    }
+
+    pub fn build(mut self) -> Result<ShoppingCart, CartError> {
+        self.cart.lines.push(self.line.item);
+        ShoppingCart::new(self.cart.lines)
+    }
+
+    fn with_line<F>(self, change: F) -> CartLineBuilder
+    where
+        F: FnOnce(ItemBuilder) -> ItemBuilder,
+    {
+        CartLineBuilder {
+            cart: self.cart,
+            line: change(self.line),
+        }
+    }
+}
@@ -0,0 +1,74 @@
+// cart![...] builds a ShoppingCart from a compact list of lines:
+//
+//   cart!["APPLE" => 3.99 x 2, "BANANA" => 2.99]
+//
+// Each line is a name, a price (a whole number will do) and an optional
+// quantity (default 1), all literals. They're checked at compile time, so
+// an empty name, a negative price or a zero quantity stops the build
+// instead of failing at runtime. The name check is the one
+// SupermarketItem::validate makes: blank means nothing but Unicode
+// whitespace, not just ASCII.
+macro_rules! cart {
+    () => {
+        $crate::ShoppingCart::new(Vec::new()).expect("an empty cart is always valid")
+    };
+    ($($name:literal => $price:literal $(x $qty:literal)?),+ $(,)?) => {
+        $crate::ShoppingCart::builder()
+            $(
+                .item(
+                    const {
+                        assert!(
+                            !$crate::cart_macro::is_blank($name),
+                            "cart!: item name is empty"
+                        );
+                        $name
+                    },
+                    const {
+                        assert!(($price as f64) >= 0.0, "cart!: price can't be negative");
+                        $price as f64
+                    },
+                )
+                .qty(const {
+                    let quantity: u32 = $crate::cart_macro::cart!(@qty $($qty)?);
+                    assert!(quantity > 0, "cart!: quantity must be at least 1");
+                    quantity
+                })
+            )+
+            .build()
+            .expect("cart! lines are checked at compile time")
+    };
+    (@qty) => {
+        1
+    };
+    (@qty $qty:literal) => {
+        $qty
+    };
+}
+
+pub(crate) use cart;
+
+// `name.trim().is_empty()`, which isn't a const fn: decodes the UTF-8 by
+// hand and checks every char with `char::is_whitespace`.
+pub(crate) const fn is_blank(name: &str) -> bool {
+    let bytes = name.as_bytes();
+    let mut at = 0;
+    while at < bytes.len() {
+        let lead = bytes[at] as u32;
+        let (mut code, width) = match lead {
+            0x00..=0x7F => (lead, 1),
+            0xC0..=0xDF => (lead & 0x1F, 2),
+            0xE0..=0xEF => (lead & 0x0F, 3),
+            _ => (lead & 0x07, 4),
+        };
+        let mut next = 1;
+        while next < width {
+            code = (code << 6) | (bytes[at + next] as u32 & 0x3F);
+            next += 1;
+        }
+        match char::from_u32(code) {
+            Some(c) if c.is_whitespace() => at += width,
+            _ => return false,
+        }
+    }
+    true
+}
@@ -0,0 +1,107 @@
+// Many live carts, keyed by session or customer id.
+// Carts that haven't been touched for `ttl` seconds are abandoned: they're
+// taken out of the store and handed, by value, to the `on_abandon` closure
+// (to send a reminder, log it, ...).
+//
+// Lookups go through a HashMap. Next to it, a BTreeSet orders every cart
+// by when it was last touched, so expiring only ever looks at the carts
+// that are actually due instead of scanning all of them.
+
+use std::collections::{BTreeSet, HashMap};
+
+use crate::ShoppingCart;
+use crate::clock::Clock;
+
+struct StoredCart {
+    cart: ShoppingCart,
+    last_touched: u64,
+}
+
+pub struct CartStore<C, F>
+where
+    C: Clock,
+    F: FnMut(&str, ShoppingCart),
+{
+    carts: HashMap<String, StoredCart>,
+    by_age: BTreeSet<(u64, String)>,
+    ttl: u64,
+    clock: C,
+    on_abandon: F,
+}
+
+impl<C, F> CartStore<C, F>
+where
+    C: Clock,
+    F: FnMut(&str, ShoppingCart),
+{
+    pub fn new(ttl: u64, clock: C, on_abandon: F) -> CartStore<C, F> {
+        CartStore {
+            carts: HashMap::new(),
+            by_age: BTreeSet::new(),
+            ttl,
+            clock,
+            on_abandon,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.carts.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.carts.is_empty()
+    }
+
+    // Stores `cart` under `id`, replacing (and returning) any cart already there.
+    pub fn insert(&mut self, id: &str, cart: ShoppingCart) -> Option<ShoppingCart> {
+        self.expire_idle();
+        let now = self.clock.now();
+        let previous = self.remove(id);
+        self.by_age.insert((now, id.to_string()));
+        self.carts.insert(
+            id.to_string(),
+            StoredCart {
+                cart,
+                last_touched: now,
+            },
+        );
+        previous
+    }
+
+    // Looking a cart up counts as activity and resets its idle time.
+    pub fn get_mut(&mut self, id: &str) -> Option<&mut ShoppingCart> {
+        self.expire_idle();
+        let now = self.clock.now();
+        let stored = self.carts.get_mut(id)?;
+        self.by_age.remove(&(stored.last_touched, id.to_string()));
+        self.by_age.insert((now, id.to_string()));
+        stored.last_touched = now;
+        Some(&mut stored.cart)
+    }
+
+    // Takes a cart out of the store, e.g. to check it out. This isn't an
+    // abandonment, so the hook isn't called.
+    pub fn remove(&mut self, id: &str) -> Option<ShoppingCart> {
+        let stored = self.carts.remove(id)?;
+        self.by_age.remove(&(stored.last_touched, id.to_string()));
+        Some(stored.cart)
+    }
+
+    // Abandons every cart idle for `ttl` or longer and returns how many.
+    pub fn expire_idle(&mut self) -> usize {
+        let now = self.clock.now();
+        let mut expired = 0;
+
+        while let Some((last_touched, _)) = self.by_age.first() {
+            if last_touched + self.ttl > now {
+                break;
+            }
+            let (_, id) = self.by_age.pop_first().expect("checked by first()");
+            if let Some(stored) = self.carts.remove(&id) {
+                (self.on_abandon)(&id, stored.cart);
+                expired += 1;
+            }
+        }
+        expired
+    }
+}
@@ -0,0 +1,76 @@
+// The store's catalog: every product it sells, keyed by SKU. Each entry
+// holds a template SupermarketItem (quantity 1) that gets copied into a
+// cart when the product is added. Barcodes map onto SKUs separately, since
+// one product can have several.
+
+use std::collections::HashMap;
+
+use crate::SupermarketItem;
+use crate::barcode::{Barcode, BarcodeError};
+use crate::error::ItemError;
+use crate::money::round_cents;
+
+#[derive(Debug)]
+pub struct CatalogEntry {
+    pub sku: String,
+    pub item: SupermarketItem,
+}
+
+#[derive(Debug, Default)]
+pub struct Catalog {
+    entries: Vec<CatalogEntry>,
+    by_sku: HashMap<String, usize>,
+    // Barcode lookup key (see Barcode::lookup_key) to SKU.
+    barcodes: HashMap<String, String>,
+}
+
+impl Catalog {
+    pub fn new() -> Catalog {
+        Catalog::default()
+    }
+
+    // Adds a product, or replaces the one already listed under `sku`.
+    pub fn add(&mut self, sku: &str, item: SupermarketItem) -> Result<(), ItemError> {
+        item.validate()?;
+        match self.by_sku.get(sku) {
+            Some(&index) => self.entries[index].item = item,
+            None => {
+                self.by_sku.insert(sku.to_string(), self.entries.len());
+                self.entries.push(CatalogEntry {
+                    sku: sku.to_string(),
+                    item,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    pub fn get(&self, sku: &str) -> Option<&CatalogEntry> {
+        self.by_sku.get(sku).map(|&index| &self.entries[index])
+    }
+
+    // Registers a barcode for `sku`. For variable-measure products, register
+    // any one code the scale prints for them; only the item code is kept.
+    pub fn add_barcode(&mut self, raw: &str, sku: &str) -> Result<(), BarcodeError> {
+        let barcode = Barcode::parse(raw)?;
+        self.barcodes.insert(barcode.lookup_key(), sku.to_string());
+        Ok(())
+    }
+
+    pub fn sku_for_barcode(&self, key: &str) -> Option<&str> {
+        self.barcodes.get(key).map(String::as_str)
+    }
+
+    pub fn entries(&self) -> &[CatalogEntry] {
+        &self.entries
+    }
+}
+
+impl SupermarketItem {
+    // Loose goods are listed in the catalog with their price per kg. This
+    // turns such a template into a line for `kg` of it.
+    pub fn price_by_weight(&mut self, kg: f64) {
+        self.price = round_cents(self.price * kg);
+        self.weight_kg = kg;
+    }
+}
@@ -0,0 +1,168 @@
+// Where the cart gets the time from.
+// Anything that depends on the time (promotions, checkout, returns, delivery
+// holds, abandoned carts) asks a Clock instead of reading the system time
+// itself. SystemClock is the real one; FakeClock only moves when it's told
+// to, so time-based rules can be tried at any moment, deterministically.
+// Any `Fn() -> u64` closure is a Clock too.
+//
+// Times are UNIX seconds. Promotions run on the store's local time, which
+// is the UTC time shifted by a fixed offset.
+
+use std::cell::Cell;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub trait Clock {
+    fn now(&self) -> u64;
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+#[derive(Debug, Default)]
+pub struct FakeClock {
+    now: Cell<u64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Weekday {
+    Monday,
+    Tuesday,
+    Wednesday,
+    Thursday,
+    Friday,
+    Saturday,
+    Sunday,
+}
+
+// A moment as the store sees it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LocalTime {
+    pub weekday: Weekday,
+    pub minute_of_day: u32,
+}
+
+impl Clock for SystemClock {
+    fn now(&self) -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("system clock is before 1970")
+            .as_secs()
+    }
+}
+
+impl FakeClock {
+    pub fn new(now: u64) -> FakeClock {
+        FakeClock {
+            now: Cell::new(now),
+        }
+    }
+
+    pub fn set(&self, now: u64) {
+        self.now.set(now);
+    }
+
+    pub fn advance(&self, seconds: u64) {
+        self.now.set(self.now.get() + seconds);
+    }
+}
+
+impl Clock for FakeClock {
+    fn now(&self) -> u64 {
+        self.now.get()
+    }
+}
+
+impl<F> Clock for F
+where
+    F: Fn() -> u64,
+{
+    fn now(&self) -> u64 {
+        self()
+    }
+}
+
+impl Weekday {
+    pub fn is_weekend(self) -> bool {
+        matches!(self, Weekday::Saturday | Weekday::Sunday)
+    }
+}
+
+impl LocalTime {
+    // `utc_offset` is in minutes, e.g. 60 for CET or -300 for EST.
+    pub fn at(timestamp: u64, utc_offset: i32) -> LocalTime {
+        let local = timestamp as i64 + utc_offset as i64 * 60;
+        let days = local.div_euclid(86_400);
+        // 1 January 1970 was a Thursday.
+        let weekday = [
+            Weekday::Thursday,
+            Weekday::Friday,
+            Weekday::Saturday,
+            Weekday::Sunday,
+            Weekday::Monday,
+            Weekday::Tuesday,
+            Weekday::Wednesday,
+        ][days.rem_euclid(7) as usize];
+        LocalTime {
+            weekday,
+            minute_of_day: (local.rem_euclid(86_400) / 60) as u32,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Friday 5 January 2024, 17:30 UTC.
+    const FRIDAY_EVENING: u64 = 1_704_475_800;
+
+    #[test]
+    fn local_time_reads_weekday_and_minute_of_day() {
+        assert_eq!(
+            LocalTime::at(FRIDAY_EVENING, 0),
+            LocalTime {
+                weekday: Weekday::Friday,
+                minute_of_day: 17 * 60 + 30,
+            }
+        );
+        // Monday 1 January 2024, midnight.
+        assert_eq!(
+            LocalTime::at(1_704_067_200, 0),
+            LocalTime {
+                weekday: Weekday::Monday,
+                minute_of_day: 0,
+            }
+        );
+    }
+
+    #[test]
+    fn local_time_applies_the_utc_offset_across_midnight() {
+        // 17:30 UTC is 12:30 in New York and 00:30 next day at UTC+7.
+        assert_eq!(
+            LocalTime::at(FRIDAY_EVENING, -300).minute_of_day,
+            12 * 60 + 30
+        );
+        let bangkok = LocalTime::at(FRIDAY_EVENING, 7 * 60);
+        assert_eq!(bangkok.weekday, Weekday::Saturday);
+        assert_eq!(bangkok.minute_of_day, 30);
+        // An hour west of UTC at the epoch is still 1969.
+        assert_eq!(
+            LocalTime::at(0, -60),
+            LocalTime {
+                weekday: Weekday::Wednesday,
+                minute_of_day: 23 * 60,
+            }
+        );
+    }
+
+    #[test]
+    fn fake_clock_only_moves_when_told() {
+        let clock = FakeClock::new(FRIDAY_EVENING);
+        assert_eq!(clock.now(), FRIDAY_EVENING);
+        clock.advance(90);
+        assert_eq!(clock.now(), FRIDAY_EVENING + 90);
+        clock.set(5);
+        assert_eq!(clock.now(), 5);
+        assert_eq!((|| 42).now(), 42);
+    }
+}
@@ -0,0 +1,270 @@
+// Delivery slot booking.
+// A shopper first places a hold on a slot, which reserves capacity for a
+// limited time. Checkout then pays and turns the hold into a booking in
+// one step: if either part fails, nothing is paid and nothing is booked.
+// Holds that aren't checked out in time simply lapse.
+//
+// The scheduler never reads the system time itself; it asks the Clock it
+// was built with, so tests can drive time by hand.
+
+use crate::ShoppingCart;
+use crate::clock::Clock;
+use crate::error::CartError;
+use crate::gift_card::{Ledger, Tender};
+use crate::policy::StorePolicy;
+use crate::receipt::Receipt;
+use crate::shipping::ShippingOption;
+
+#[derive(Debug)]
+pub struct Slot {
+    pub starts_at: u64,
+    pub ends_at: u64,
+    pub capacity: u32,
+    // No new holds can be placed on the slot from this moment on.
+    pub cutoff: u64,
+}
+
+#[derive(Debug)]
+struct Hold {
+    id: u64,
+    slot: usize,
+    session: String,
+    expires_at: u64,
+}
+
+#[derive(Debug, Clone)]
+pub struct Booking {
+    pub slot: usize,
+    pub session: String,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum SlotError {
+    UnknownSlot(usize),
+    PastCutoff,
+    Full,
+    // The hold never existed, has lapsed, or was already used.
+    HoldNotFound(u64),
+}
+
+pub struct SlotScheduler<C>
+where
+    C: Clock,
+{
+    slots: Vec<Slot>,
+    holds: Vec<Hold>,
+    bookings: Vec<Booking>,
+    hold_ttl: u64,
+    next_hold: u64,
+    clock: C,
+}
+
+impl<C> SlotScheduler<C>
+where
+    C: Clock,
+{
+    pub fn new(hold_ttl: u64, clock: C) -> SlotScheduler<C> {
+        SlotScheduler {
+            slots: Vec::new(),
+            holds: Vec::new(),
+            bookings: Vec::new(),
+            hold_ttl,
+            next_hold: 1,
+            clock,
+        }
+    }
+
+    pub fn now(&self) -> u64 {
+        self.clock.now()
+    }
+
+    pub fn add_slot(&mut self, slot: Slot) -> usize {
+        self.slots.push(slot);
+        self.slots.len() - 1
+    }
+
+    fn expire_holds(&mut self) {
+        let now = self.now();
+        self.holds.retain(|hold| hold.expires_at > now);
+    }
+
+    fn taken(&self, slot: usize) -> u32 {
+        let held = self.holds.iter().filter(|hold| hold.slot == slot).count();
+        let booked = self
+            .bookings
+            .iter()
+            .filter(|booking| booking.slot == slot)
+            .count();
+        (held + booked) as u32
+    }
+
+    // Slots that can still take a hold right now, with their free capacity.
+    pub fn available(&mut self) -> Vec<(usize, &Slot, u32)> {
+        self.expire_holds();
+        let now = self.now();
+        self.slots
+            .iter()
+            .enumerate()
+            .filter(|(_, slot)| now < slot.cutoff)
+            .map(|(index, slot)| (index, slot, slot.capacity - self.taken(index)))
+            .filter(|(_, _, free)| *free > 0)
+            .collect()
+    }
+
+    pub fn hold(&mut self, slot: usize, session: &str) -> Result<u64, SlotError> {
+        self.expire_holds();
+        let now = self.now();
+        let cutoff = self
+            .slots
+            .get(slot)
+            .ok_or(SlotError::UnknownSlot(slot))?
+            .cutoff;
+        if now >= cutoff {
+            return Err(SlotError::PastCutoff);
+        }
+        if self.taken(slot) >= self.slots[slot].capacity {
+            return Err(SlotError::Full);
+        }
+
+        let id = self.next_hold;
+        self.next_hold += 1;
+        self.holds.push(Hold {
+            id,
+            slot,
+            session: session.to_string(),
+            expires_at: now + self.hold_ttl,
+        });
+        Ok(id)
+    }
+
+    pub fn release(&mut self, hold: u64) {
+        self.holds.retain(|existing| existing.id != hold);
+    }
+
+    pub fn bookings(&self) -> &[Booking] {
+        &self.bookings
+    }
+}
+
+impl ShoppingCart {
+    // Pays for the cart and books the held delivery slot together. The hold
+    // is checked before any tender is touched, and payment is taken before
+    // the hold is turned into a booking, so the two can't get out of step.
+    // Holds and payment both go by the scheduler's clock.
+    pub fn checkout_with_delivery<C>(
+        self,
+        scheduler: &mut SlotScheduler<C>,
+        hold: u64,
+        policy: &StorePolicy,
+        shipping: Option<ShippingOption>,
+        tenders: Vec<Tender>,
+        ledger: &mut Ledger,
+    ) -> Result<(Receipt, Booking), CartError>
+    where
+        C: Clock,
+    {
+        scheduler.expire_holds();
+        let position = scheduler
+            .holds
+            .iter()
+            .position(|existing| existing.id == hold)
+            .ok_or(SlotError::HoldNotFound(hold))?;
+
+        let receipt =
+            self.checkout_with_tenders(policy, shipping, tenders, ledger, &scheduler.clock)?;
+
+        let hold = scheduler.holds.remove(position);
+        let booking = Booking {
+            slot: hold.slot,
+            session: hold.session,
+        };
+        scheduler.bookings.push(booking.clone());
+        Ok((receipt, booking))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::FakeClock;
+
+    const NOW: u64 = 1_704_475_800;
+    const TTL: u64 = 15 * 60;
+
+    fn scheduler(clock: &FakeClock) -> SlotScheduler<impl Clock + '_> {
+        let mut scheduler = SlotScheduler::new(TTL, || clock.now());
+        scheduler.add_slot(Slot {
+            starts_at: NOW + 86_400,
+            ends_at: NOW + 86_400 + 7_200,
+            capacity: 1,
+            cutoff: NOW + 3_600,
+        });
+        scheduler
+    }
+
+    fn checkout<C: Clock>(
+        scheduler: &mut SlotScheduler<C>,
+        hold: u64,
+    ) -> Result<(Receipt, Booking), CartError> {
+        let cart = ShoppingCart::new(Vec::new()).unwrap();
+        let policy = StorePolicy::new(0.0, 0).unwrap();
+        cart.checkout_with_delivery(
+            scheduler,
+            hold,
+            &policy,
+            None,
+            Vec::new(),
+            &mut Ledger::new(),
+        )
+    }
+
+    #[test]
+    fn a_hold_lapses_after_its_time_to_live() {
+        let clock = FakeClock::new(NOW);
+        let mut scheduler = scheduler(&clock);
+
+        let first = scheduler.hold(0, "ana").unwrap();
+        assert_eq!(scheduler.hold(0, "ben"), Err(SlotError::Full));
+
+        // Still held one second before it lapses.
+        clock.advance(TTL - 1);
+        assert!(scheduler.available().is_empty());
+        clock.advance(1);
+        assert_eq!(scheduler.available().len(), 1);
+
+        let second = scheduler.hold(0, "ben").unwrap();
+        assert!(matches!(
+            checkout(&mut scheduler, first),
+            Err(CartError::Slot(SlotError::HoldNotFound(id))) if id == first
+        ));
+        let (receipt, booking) = checkout(&mut scheduler, second).unwrap();
+        assert_eq!(receipt.issued_at, NOW + TTL);
+        assert_eq!(booking.session, "ben");
+
+        // A booking keeps its place however long it's been.
+        clock.advance(TTL * 2);
+        assert_eq!(scheduler.hold(0, "cleo"), Err(SlotError::Full));
+    }
+
+    #[test]
+    fn no_holds_from_the_cutoff_on() {
+        let clock = FakeClock::new(NOW + 3_600 - 1);
+        let mut scheduler = scheduler(&clock);
+        assert_eq!(scheduler.available().len(), 1);
+
+        clock.advance(1);
+        assert!(scheduler.available().is_empty());
+        assert_eq!(scheduler.hold(0, "ana"), Err(SlotError::PastCutoff));
+        assert_eq!(scheduler.hold(3, "ana"), Err(SlotError::UnknownSlot(3)));
+    }
+
+    #[test]
+    fn a_released_hold_frees_its_place() {
+        let clock = FakeClock::new(NOW);
+        let mut scheduler = scheduler(&clock);
+
+        let hold = scheduler.hold(0, "ana").unwrap();
+        scheduler.release(hold);
+        assert!(scheduler.hold(0, "ben").is_ok());
+    }
+}
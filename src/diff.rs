@@ -0,0 +1,197 @@
+// A structured diff between two carts, e.g. before and after an edit or a
+// traverse_items closure. Lines are matched up by normalised product name
+// (see normalize.rs), so a change of case or spacing isn't a new line, but
+// it still shows up as a rename. A product that is both repriced and
+// requantified shows up as two changes.
+
+use std::fmt;
+
+use crate::ShoppingCart;
+
+#[derive(Debug, PartialEq)]
+pub enum LineChange {
+    Added {
+        name: String,
+        price: f64,
+        quantity: u32,
+    },
+    Removed {
+        name: String,
+        price: f64,
+        quantity: u32,
+    },
+    Renamed {
+        from: String,
+        to: String,
+    },
+    Repriced {
+        name: String,
+        from: f64,
+        to: f64,
+    },
+    Quantity {
+        name: String,
+        from: u32,
+        to: u32,
+    },
+}
+
+#[derive(Debug, PartialEq)]
+pub struct CartDiff {
+    pub changes: Vec<LineChange>,
+}
+
+impl ShoppingCart {
+    // What it takes to turn `self` into `other`.
+    pub fn diff(&self, other: &ShoppingCart) -> CartDiff {
+        let mut changes = Vec::new();
+        let mut matched = vec![false; other.item.len()];
+
+        for before in &self.item {
+            let after = other
+                .item
+                .iter()
+                .enumerate()
+                .position(|(index, after)| !matched[index] && after.same_product(before));
+
+            let Some(index) = after else {
+                changes.push(LineChange::Removed {
+                    name: before.name.clone(),
+                    price: before.price,
+                    quantity: before.quantity,
+                });
+                continue;
+            };
+
+            matched[index] = true;
+            let after = &other.item[index];
+            if after.name != before.name {
+                changes.push(LineChange::Renamed {
+                    from: before.name.clone(),
+                    to: after.name.clone(),
+                });
+            }
+            if after.price != before.price {
+                changes.push(LineChange::Repriced {
+                    name: before.name.clone(),
+                    from: before.price,
+                    to: after.price,
+                });
+            }
+            if after.quantity != before.quantity {
+                changes.push(LineChange::Quantity {
+                    name: before.name.clone(),
+                    from: before.quantity,
+                    to: after.quantity,
+                });
+            }
+        }
+
+        for (index, after) in other.item.iter().enumerate() {
+            if !matched[index] {
+                changes.push(LineChange::Added {
+                    name: after.name.clone(),
+                    price: after.price,
+                    quantity: after.quantity,
+                });
+            }
+        }
+
+        CartDiff { changes }
+    }
+}
+
+impl CartDiff {
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+}
+
+impl fmt::Display for LineChange {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LineChange::Added {
+                name,
+                price,
+                quantity,
+            } => write!(f, "+ {} x{} @ {:.2}", name, quantity, price),
+            LineChange::Removed {
+                name,
+                price,
+                quantity,
+            } => write!(f, "- {} x{} @ {:.2}", name, quantity, price),
+            LineChange::Renamed { from, to } => {
+                write!(f, "~ {} renamed -> {}", from, to)
+            }
+            LineChange::Repriced { name, from, to } => {
+                write!(f, "~ {} price {:.2} -> {:.2}", name, from, to)
+            }
+            LineChange::Quantity { name, from, to } => {
+                write!(f, "~ {} quantity {} -> {}", name, from, to)
+            }
+        }
+    }
+}
+
+impl fmt::Display for CartDiff {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_empty() {
+            return write!(f, "(no changes)");
+        }
+        let lines: Vec<String> = self
+            .changes
+            .iter()
+            .map(|change| change.to_string())
+            .collect();
+        write!(f, "{}", lines.join("\n"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SupermarketItem;
+
+    fn cart() -> ShoppingCart {
+        ShoppingCart::new(vec![
+            SupermarketItem::new("MILK", 4.49).unwrap(),
+            SupermarketItem::new("BREAD", 2.99).unwrap(),
+        ])
+        .unwrap()
+    }
+
+    #[test]
+    fn a_change_of_case_is_a_rename_not_a_new_line() {
+        let before = cart();
+        let mut after = before.clone();
+        after
+            .traverse_items(|item| item.name = item.name.to_lowercase())
+            .unwrap();
+
+        let diff = before.diff(&after);
+        assert_eq!(
+            diff.changes,
+            vec![
+                LineChange::Renamed {
+                    from: "MILK".to_string(),
+                    to: "milk".to_string(),
+                },
+                LineChange::Renamed {
+                    from: "BREAD".to_string(),
+                    to: "bread".to_string(),
+                },
+            ]
+        );
+        assert_eq!(
+            diff.to_string(),
+            "~ MILK renamed -> milk\n~ BREAD renamed -> bread"
+        );
+    }
+
+    #[test]
+    fn an_untouched_cart_has_no_changes() {
+        let cart = cart();
+        assert!(cart.diff(&cart).is_empty());
+        assert_eq!(cart.diff(&cart).to_string(), "(no changes)");
+    }
+}
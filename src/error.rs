@@ -0,0 +1,157 @@
+// Everything that can go wrong with an item or a cart.
+// Each feature keeps its own small error enum; CartError wraps all of them
+// so callers that drive a whole checkout only have one type to handle.
+
+use std::fmt;
+
+use crate::age_check::AgeError;
+use crate::barcode::BarcodeError;
+use crate::delivery::SlotError;
+use crate::gift_card::PaymentError;
+use crate::policy::PolicyError;
+use crate::promotions::PromotionError;
+use crate::returns::ReturnError;
+use crate::self_checkout::Intervention;
+use crate::shipping::ShippingError;
+use crate::split::SplitError;
+
+#[derive(Debug, PartialEq)]
+pub enum ItemError {
+    EmptyName,
+    // NaN, infinite or negative.
+    InvalidPrice(f64),
+    ZeroQuantity,
+    InvalidWeight(f64),
+    InvalidDimensions([f64; 3]),
+    EmptyCategory,
+    InvalidPackSize(f64),
+    InvalidNutrition,
+    // The list price a price cut was taken from: NaN, infinite or negative.
+    InvalidListPrice(f64),
+    InvalidMultiBuy { buy: u32, pay: u32 },
+}
+
+#[derive(Debug)]
+pub enum CartError {
+    // An item that failed validation before it was ever put in a cart.
+    InvalidItem(ItemError),
+    // An item already in (or being put into) a cart, at that line.
+    Item { line: usize, error: ItemError },
+    NoSuchLine(usize),
+    UnknownSku(String),
+    Barcode(BarcodeError),
+    // A self-checkout weight mismatch that no attendant has cleared yet.
+    InterventionRequired(Intervention),
+    Age(AgeError),
+    Payment(PaymentError),
+    Split(SplitError),
+    Shipping(ShippingError),
+    Slot(SlotError),
+    Return(ReturnError),
+    Policy(PolicyError),
+    Promotion(PromotionError),
+}
+
+impl fmt::Display for ItemError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ItemError::EmptyName => write!(f, "item name is empty"),
+            ItemError::InvalidPrice(price) => write!(f, "invalid price {}", price),
+            ItemError::ZeroQuantity => write!(f, "quantity must be at least 1"),
+            ItemError::InvalidWeight(weight) => write!(f, "invalid weight {} kg", weight),
+            ItemError::InvalidDimensions(dimensions) => {
+                write!(f, "invalid dimensions {:?} cm", dimensions)
+            }
+            ItemError::EmptyCategory => write!(f, "category is empty"),
+            ItemError::InvalidPackSize(amount) => write!(f, "invalid pack size {}", amount),
+            ItemError::InvalidNutrition => write!(f, "nutrition facts must be finite and >= 0"),
+            ItemError::InvalidListPrice(price) => write!(f, "invalid list price {}", price),
+            ItemError::InvalidMultiBuy { buy, pay } => {
+                write!(f, "invalid multi-buy: buy {}, pay {}", buy, pay)
+            }
+        }
+    }
+}
+
+impl fmt::Display for CartError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CartError::InvalidItem(error) => write!(f, "{}", error),
+            CartError::Item { line, error } => write!(f, "line {}: {}", line, error),
+            CartError::NoSuchLine(line) => write!(f, "there is no line {}", line),
+            CartError::UnknownSku(sku) => write!(f, "no product with SKU {}", sku),
+            CartError::Barcode(error) => write!(f, "barcode: {:?}", error),
+            CartError::InterventionRequired(intervention) => {
+                write!(f, "attendant needed: {:?}", intervention)
+            }
+            CartError::Age(error) => write!(f, "age check failed: {:?}", error),
+            CartError::Payment(error) => write!(f, "payment failed: {:?}", error),
+            CartError::Split(error) => write!(f, "couldn't split the bill: {:?}", error),
+            CartError::Shipping(error) => write!(f, "shipping: {:?}", error),
+            CartError::Slot(error) => write!(f, "delivery slot: {:?}", error),
+            CartError::Return(error) => write!(f, "return refused: {:?}", error),
+            CartError::Policy(error) => write!(f, "store policy: {:?}", error),
+            CartError::Promotion(error) => write!(f, "promotion: {:?}", error),
+        }
+    }
+}
+
+impl From<ItemError> for CartError {
+    fn from(error: ItemError) -> CartError {
+        CartError::InvalidItem(error)
+    }
+}
+
+impl From<BarcodeError> for CartError {
+    fn from(error: BarcodeError) -> CartError {
+        CartError::Barcode(error)
+    }
+}
+
+impl From<AgeError> for CartError {
+    fn from(error: AgeError) -> CartError {
+        CartError::Age(error)
+    }
+}
+
+impl From<PaymentError> for CartError {
+    fn from(error: PaymentError) -> CartError {
+        CartError::Payment(error)
+    }
+}
+
+impl From<SplitError> for CartError {
+    fn from(error: SplitError) -> CartError {
+        CartError::Split(error)
+    }
+}
+
+impl From<ShippingError> for CartError {
+    fn from(error: ShippingError) -> CartError {
+        CartError::Shipping(error)
+    }
+}
+
+impl From<SlotError> for CartError {
+    fn from(error: SlotError) -> CartError {
+        CartError::Slot(error)
+    }
+}
+
+impl From<ReturnError> for CartError {
+    fn from(error: ReturnError) -> CartError {
+        CartError::Return(error)
+    }
+}
+
+impl From<PolicyError> for CartError {
+    fn from(error: PolicyError) -> CartError {
+        CartError::Policy(error)
+    }
+}
+
+impl From<PromotionError> for CartError {
+    fn from(error: PromotionError) -> CartError {
+        CartError::Promotion(error)
+    }
+}
@@ -0,0 +1,297 @@
+// Gift cards and store credit, usable as (partial) payment at checkout.
+// Every balance change goes through the Ledger so finance can reconcile
+// the balances against the history of what happened to them.
+
+use std::fmt;
+
+use crate::ShoppingCart;
+use crate::clock::Clock;
+use crate::money::round_cents;
+use crate::policy::{StorePolicy, points_for};
+use crate::receipt::{Payment, Receipt, ReceiptLine};
+use crate::shipping::ShippingOption;
+
+pub const STORE_CURRENCY: &str = "USD";
+
+#[derive(Debug)]
+pub struct GiftCard {
+    pub code: String,
+    pub balance: f64,
+    pub currency: String,
+    // Seconds since the UNIX epoch; the card can't be used from then on.
+    pub expires_at: u64,
+}
+
+#[derive(Debug)]
+pub struct StoreCredit {
+    pub customer_id: String,
+    pub balance: f64,
+}
+
+#[derive(Debug)]
+pub struct LedgerEntry {
+    pub account: String,
+    pub change: f64,
+    pub balance_after: f64,
+    pub reason: String,
+    pub at: u64,
+}
+
+#[derive(Debug, Default)]
+pub struct Ledger {
+    entries: Vec<LedgerEntry>,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum PaymentError {
+    Expired(String),
+    // An age-restricted line was never covered by a successful ID check.
+    AgeNotVerified(String),
+    CurrencyMismatch { code: String, currency: String },
+    // A negative, NaN or infinite amount to issue or credit.
+    InvalidAmount(f64),
+}
+
+fn check_amount(amount: f64) -> Result<f64, PaymentError> {
+    if !amount.is_finite() || amount < 0.0 {
+        return Err(PaymentError::InvalidAmount(amount));
+    }
+    Ok(round_cents(amount))
+}
+
+// One way of paying for (part of) a cart. The tender borrows the card or
+// credit mutably so checkout can draw its balance down in place.
+pub enum Tender<'a> {
+    GiftCard(&'a mut GiftCard),
+    StoreCredit(&'a mut StoreCredit),
+}
+
+impl GiftCard {
+    pub fn issue<C>(
+        code: &str,
+        amount: f64,
+        currency: &str,
+        expires_at: u64,
+        ledger: &mut Ledger,
+        clock: &C,
+    ) -> Result<GiftCard, PaymentError>
+    where
+        C: Clock,
+    {
+        let card = GiftCard {
+            code: code.to_string(),
+            balance: check_amount(amount)?,
+            currency: currency.to_string(),
+            expires_at,
+        };
+        ledger.record(
+            &card.account(),
+            card.balance,
+            card.balance,
+            "issued",
+            clock.now(),
+        );
+        Ok(card)
+    }
+
+    pub fn account(&self) -> String {
+        format!("gift-card:{}", self.code)
+    }
+
+    fn is_expired(&self, now: u64) -> bool {
+        now >= self.expires_at
+    }
+}
+
+impl StoreCredit {
+    pub fn new(customer_id: &str) -> StoreCredit {
+        StoreCredit {
+            customer_id: customer_id.to_string(),
+            balance: 0.0,
+        }
+    }
+
+    pub fn account(&self) -> String {
+        format!("store-credit:{}", self.customer_id)
+    }
+
+    // Crediting nothing (a refund of a free item) leaves no ledger entry.
+    pub fn credit<C>(
+        &mut self,
+        amount: f64,
+        reason: &str,
+        ledger: &mut Ledger,
+        clock: &C,
+    ) -> Result<(), PaymentError>
+    where
+        C: Clock,
+    {
+        let amount = check_amount(amount)?;
+        if amount == 0.0 {
+            return Ok(());
+        }
+        self.balance = round_cents(self.balance + amount);
+        ledger.record(&self.account(), amount, self.balance, reason, clock.now());
+        Ok(())
+    }
+}
+
+impl Ledger {
+    pub fn new() -> Ledger {
+        Ledger::default()
+    }
+
+    pub fn record(
+        &mut self,
+        account: &str,
+        change: f64,
+        balance_after: f64,
+        reason: &str,
+        at: u64,
+    ) {
+        self.entries.push(LedgerEntry {
+            account: account.to_string(),
+            change,
+            balance_after,
+            reason: reason.to_string(),
+            at,
+        });
+    }
+
+    pub fn entries(&self) -> &[LedgerEntry] {
+        &self.entries
+    }
+
+    // The balance an account should have according to its history.
+    // Finance compares this with the live balance on the card or credit.
+    pub fn reconcile(&self, account: &str) -> f64 {
+        let total = self
+            .entries
+            .iter()
+            .filter(|entry| entry.account == account)
+            .map(|entry| entry.change)
+            .sum();
+        round_cents(total)
+    }
+}
+
+impl fmt::Display for LedgerEntry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} {:<24} {:>8.2} -> {:>8.2} ({})",
+            self.at, self.account, self.change, self.balance_after, self.reason
+        )
+    }
+}
+
+impl Tender<'_> {
+    fn validate(&self, now: u64) -> Result<(), PaymentError> {
+        match self {
+            Tender::GiftCard(card) if card.is_expired(now) => {
+                Err(PaymentError::Expired(card.code.clone()))
+            }
+            Tender::GiftCard(card) if card.currency != STORE_CURRENCY => {
+                Err(PaymentError::CurrencyMismatch {
+                    code: card.code.clone(),
+                    currency: card.currency.clone(),
+                })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    // Takes as much as the tender can cover of `remaining` and returns the
+    // amount that was actually drawn.
+    fn draw(&mut self, remaining: f64, ledger: &mut Ledger, now: u64) -> Payment {
+        let (account, balance) = match self {
+            Tender::GiftCard(card) => (card.account(), &mut card.balance),
+            Tender::StoreCredit(credit) => (credit.account(), &mut credit.balance),
+        };
+
+        let amount = round_cents(balance.min(remaining));
+        // An empty card or credit pays nothing and leaves no ledger entry.
+        if amount > 0.0 {
+            *balance = round_cents(*balance - amount);
+            ledger.record(&account, -amount, *balance, "checkout", now);
+        }
+
+        Payment {
+            method: account,
+            amount,
+        }
+    }
+}
+
+impl ShoppingCart {
+    // Pays for the cart with any number of gift cards and store credits,
+    // in the order they're given. Tax from `policy` and shipping, when there
+    // is any, are added to the total before the tenders are drawn.
+    // Age-restricted lines must have passed `verify_age` first. Every
+    // tender is checked before any balance is touched, so a bad card leaves
+    // all the others untouched.
+    pub fn checkout_with_tenders<C>(
+        self,
+        policy: &StorePolicy,
+        shipping: Option<ShippingOption>,
+        mut tenders: Vec<Tender>,
+        ledger: &mut Ledger,
+        clock: &C,
+    ) -> Result<Receipt, PaymentError>
+    where
+        C: Clock,
+    {
+        let now = clock.now();
+        if let Some(item) = self.unverified_line() {
+            return Err(PaymentError::AgeNotVerified(item.name.clone()));
+        }
+        for tender in &tenders {
+            tender.validate(now)?;
+        }
+
+        let lines: Vec<ReceiptLine> = self
+            .item
+            .into_iter()
+            .map(|item| ReceiptLine {
+                tax_rate: policy.tax_rate(&item),
+                list_price: item.list_price(),
+                price_cut: item.price_cut.map(|cut| cut.name),
+                multi_buy: item.multi_buy,
+                name: item.name,
+                price: item.price,
+                quantity: item.quantity,
+                returned: 0,
+            })
+            .collect();
+        let goods = round_cents(lines.iter().map(|line| line.net(line.quantity)).sum());
+        let tax = round_cents(lines.iter().map(|line| line.tax(line.quantity)).sum());
+
+        let shipping_cost = shipping.as_ref().map_or(0.0, |option| option.cost);
+        let total = round_cents(goods + tax + shipping_cost);
+        let mut remaining = total;
+        let mut payments = Vec::new();
+
+        for tender in tenders.iter_mut() {
+            if remaining <= 0.0 {
+                break;
+            }
+            let payment = tender.draw(remaining, ledger, now);
+            if payment.amount > 0.0 {
+                remaining = round_cents(remaining - payment.amount);
+                payments.push(payment);
+            }
+        }
+
+        Ok(Receipt {
+            lines,
+            shipping,
+            tax,
+            total,
+            payments,
+            amount_due: remaining,
+            issued_at: now,
+            points: points_for(goods, policy.points_per_unit),
+            points_per_unit: policy.points_per_unit,
+        })
+    }
+}
@@ -0,0 +1,98 @@
+// Grouping and ordering cart lines, up to the order a shopper walks the
+// store in. An item's aisle is its own `aisle` if it has one, otherwise
+// the aisle the store layout puts its category in.
+
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+use crate::{ShoppingCart, SupermarketItem};
+
+#[derive(Debug, Default)]
+pub struct StoreLayout {
+    // Aisle numbers in the order they're walked past from the entrance.
+    walk: Vec<u32>,
+    category_aisles: HashMap<String, u32>,
+}
+
+impl StoreLayout {
+    pub fn new(walk: Vec<u32>) -> StoreLayout {
+        StoreLayout {
+            walk,
+            category_aisles: HashMap::new(),
+        }
+    }
+
+    pub fn place(&mut self, category: &str, aisle: u32) {
+        self.category_aisles.insert(category.to_string(), aisle);
+    }
+
+    pub fn aisle_of(&self, item: &SupermarketItem) -> Option<u32> {
+        item.aisle.or_else(|| {
+            let category = item.category.as_ref()?;
+            self.category_aisles.get(category).copied()
+        })
+    }
+
+    // Where on the walk the item is picked up; items the layout can't
+    // place come last.
+    fn stop(&self, item: &SupermarketItem) -> usize {
+        self.aisle_of(item)
+            .and_then(|aisle| self.walk.iter().position(|stop| *stop == aisle))
+            .unwrap_or(self.walk.len())
+    }
+}
+
+impl ShoppingCart {
+    // Lines grouped by whatever the closure returns, groups in the order
+    // their first line appears in the cart.
+    pub fn group_by<K, F>(&self, mut key: F) -> Vec<(K, Vec<&SupermarketItem>)>
+    where
+        K: PartialEq,
+        F: FnMut(&SupermarketItem) -> K,
+    {
+        let mut groups: Vec<(K, Vec<&SupermarketItem>)> = Vec::new();
+        for item in &self.item {
+            let item_key = key(item);
+            match groups
+                .iter_mut()
+                .find(|(existing, _)| *existing == item_key)
+            {
+                Some((_, items)) => items.push(item),
+                None => groups.push((item_key, vec![item])),
+            }
+        }
+        groups
+    }
+
+    // Stable, so lines that compare equal keep their order.
+    pub fn sort_lines_by<F>(&mut self, compare: F)
+    where
+        F: FnMut(&SupermarketItem, &SupermarketItem) -> Ordering,
+    {
+        self.item.sort_by(compare);
+    }
+
+    // By category name, uncategorised lines last.
+    pub fn sort_by_category(&mut self) {
+        self.sort_lines_by(|a, b| match (&a.category, &b.category) {
+            (Some(a), Some(b)) => a.cmp(b),
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => Ordering::Equal,
+        });
+    }
+
+    // By aisle number, lines without an aisle last.
+    pub fn sort_by_aisle(&mut self) {
+        self.sort_lines_by(|a, b| {
+            a.aisle
+                .unwrap_or(u32::MAX)
+                .cmp(&b.aisle.unwrap_or(u32::MAX))
+        });
+    }
+
+    // Into the order the shopper will reach each item walking the store.
+    pub fn sort_for_walk(&mut self, layout: &StoreLayout) {
+        self.item.sort_by_key(|item| layout.stop(item));
+    }
+}
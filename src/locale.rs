@@ -0,0 +1,199 @@
+// Locale-aware formatting for money, quantities and dates.
+// The locale tables are bundled below, so formatting works offline. Dates
+// are printed in UTC.
+
+use std::env;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DateOrder {
+    MonthDayYear,
+    DayMonthYear,
+    YearMonthDay,
+}
+
+#[derive(Debug)]
+pub struct Locale {
+    pub tag: &'static str,
+    decimal: char,
+    group: &'static str,
+    // Whether the currency symbol goes before the amount, and if a space
+    // separates them.
+    symbol_first: bool,
+    symbol_space: bool,
+    date_order: DateOrder,
+    date_separator: char,
+}
+
+// (code, symbol, decimals)
+const CURRENCIES: &[(&str, &str, usize)] = &[
+    ("USD", "$", 2),
+    ("EUR", "€", 2),
+    ("GBP", "£", 2),
+    ("JPY", "¥", 0),
+    ("CHF", "CHF", 2),
+];
+
+pub const LOCALES: &[Locale] = &[
+    Locale {
+        tag: "en-US",
+        decimal: '.',
+        group: ",",
+        symbol_first: true,
+        symbol_space: false,
+        date_order: DateOrder::MonthDayYear,
+        date_separator: '/',
+    },
+    Locale {
+        tag: "en-GB",
+        decimal: '.',
+        group: ",",
+        symbol_first: true,
+        symbol_space: false,
+        date_order: DateOrder::DayMonthYear,
+        date_separator: '/',
+    },
+    Locale {
+        tag: "de-DE",
+        decimal: ',',
+        group: ".",
+        symbol_first: false,
+        symbol_space: true,
+        date_order: DateOrder::DayMonthYear,
+        date_separator: '.',
+    },
+    Locale {
+        tag: "fr-FR",
+        decimal: ',',
+        group: "\u{202f}",
+        symbol_first: false,
+        symbol_space: true,
+        date_order: DateOrder::DayMonthYear,
+        date_separator: '/',
+    },
+    Locale {
+        tag: "ja-JP",
+        decimal: '.',
+        group: ",",
+        symbol_first: true,
+        symbol_space: false,
+        date_order: DateOrder::YearMonthDay,
+        date_separator: '/',
+    },
+];
+
+// The symbol a currency is shown with, e.g. "$" for USD.
+pub fn currency_symbol(currency: &str) -> Option<&'static str> {
+    CURRENCIES
+        .iter()
+        .find(|(code, _, _)| *code == currency)
+        .map(|(_, symbol, _)| *symbol)
+}
+
+// The currency whose symbol or code `text` starts or ends with, e.g. EUR
+// for "€1.59" or "1.59 EUR".
+pub fn currency_in(text: &str) -> Option<&'static str> {
+    CURRENCIES
+        .iter()
+        .find(|(code, symbol, _)| {
+            [code, symbol]
+                .iter()
+                .any(|mark| text.starts_with(**mark) || text.ends_with(**mark))
+        })
+        .map(|(code, _, _)| *code)
+}
+
+// Days since 1970-01-01 to a (year, month, day) civil date.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+impl Locale {
+    // Accepts "de-DE" as well as the POSIX form "de_DE.UTF-8".
+    pub fn get(tag: &str) -> Option<&'static Locale> {
+        let tag = tag.split('.').next().unwrap_or("").replace('_', "-");
+        LOCALES
+            .iter()
+            .find(|locale| locale.tag.eq_ignore_ascii_case(&tag))
+    }
+
+    pub fn default_locale() -> &'static Locale {
+        &LOCALES[0]
+    }
+
+    // The locale from LC_ALL or LANG, falling back to en-US.
+    pub fn from_env() -> &'static Locale {
+        ["LC_ALL", "LANG"]
+            .iter()
+            .filter_map(|name| env::var(name).ok())
+            .find_map(|value| Locale::get(&value))
+            .unwrap_or(Locale::default_locale())
+    }
+
+    pub fn format_number(&self, value: f64, decimals: usize) -> String {
+        // Round half away from zero, like a till would, rather than letting
+        // the formatter round half to even.
+        let factor = 10f64.powi(decimals as i32);
+        let text = format!("{:.*}", decimals, (value.abs() * factor).round() / factor);
+        let (whole, fraction) = text.split_once('.').unwrap_or((&text, ""));
+
+        let mut grouped = String::new();
+        for (index, digit) in whole.chars().enumerate() {
+            if index > 0 && (whole.len() - index) % 3 == 0 {
+                grouped.push_str(self.group);
+            }
+            grouped.push(digit);
+        }
+
+        let sign = if value < 0.0 && text.chars().any(|ch| ch.is_ascii_digit() && ch != '0') {
+            "-"
+        } else {
+            ""
+        };
+        if fraction.is_empty() {
+            format!("{}{}", sign, grouped)
+        } else {
+            format!("{}{}{}{}", sign, grouped, self.decimal, fraction)
+        }
+    }
+
+    // Unknown currency codes are shown as the code itself, with 2 decimals.
+    pub fn format_money(&self, amount: f64, currency: &str) -> String {
+        let (symbol, decimals) = CURRENCIES
+            .iter()
+            .find(|(code, _, _)| *code == currency)
+            .map_or((currency, 2), |(_, symbol, decimals)| (*symbol, *decimals));
+        let number = self.format_number(amount, decimals);
+        let space = if self.symbol_space { "\u{a0}" } else { "" };
+
+        match (self.symbol_first, number.strip_prefix('-')) {
+            (true, Some(positive)) => format!("-{}{}{}", symbol, space, positive),
+            (true, None) => format!("{}{}{}", symbol, space, number),
+            (false, _) => format!("{}{}{}", number, space, symbol),
+        }
+    }
+
+    pub fn format_quantity(&self, amount: f64, decimals: usize, unit: &str) -> String {
+        format!("{}\u{a0}{}", self.format_number(amount, decimals), unit)
+    }
+
+    // A UNIX timestamp as a date in this locale's order, e.g. 10/18/2026,
+    // 18.10.2026 or 2026/10/18.
+    pub fn format_date(&self, timestamp: u64) -> String {
+        let (year, month, day) = civil_from_days((timestamp / 86_400) as i64);
+        let sep = self.date_separator;
+        match self.date_order {
+            DateOrder::MonthDayYear => format!("{:02}{sep}{:02}{sep}{}", month, day, year),
+            DateOrder::DayMonthYear => format!("{:02}{sep}{:02}{sep}{}", day, month, year),
+            DateOrder::YearMonthDay => format!("{}{sep}{:02}{sep}{:02}", year, month, day),
+        }
+    }
+}
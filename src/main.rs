@@ -1,61 +1,889 @@
-#[derive(Debug)]
+mod age_check;
+mod barcode;
+mod builder;
+mod cart_macro;
+mod cart_store;
+mod catalog;
+mod clock;
+mod delivery;
+mod diff;
+mod error;
+mod gift_card;
+mod layout;
+mod locale;
+mod money;
+mod normalize;
+mod nutrition;
+mod policy;
+mod price_history;
+mod price_list;
+mod promotions;
+mod receipt;
+mod recommend;
+mod returns;
+mod search;
+mod self_checkout;
+mod shipping;
+mod shopping_list;
+mod split;
+mod unit_price;
+mod wishlist;
+
+use std::cell::Cell;
+use std::env;
+use std::time::Instant;
+
+use age_check::AgeError;
+use cart_macro::cart;
+use cart_store::CartStore;
+use catalog::Catalog;
+use clock::{Clock, FakeClock, LocalTime, SystemClock};
+use delivery::{Slot, SlotScheduler};
+use error::{CartError, ItemError};
+use gift_card::{GiftCard, Ledger, STORE_CURRENCY, StoreCredit, Tender};
+use layout::StoreLayout;
+use locale::Locale;
+use money::round_cents;
+use normalize::NormalizeOptions;
+use nutrition::{Allergen, DietTag, Nutrition};
+use policy::{LoyaltyAccount, StorePolicy};
+use price_history::{PriceHistory, PriceSource};
+use price_list::{Column, ColumnMapping, PriceList};
+use promotions::{MultiBuy, PriceCut, Promotions, Schedule};
+use recommend::ReceiptArchive;
+use returns::DEFAULT_RETURN_WINDOW;
+use search::SearchIndex;
+use self_checkout::SelfCheckout;
+use shipping::ShippingConfig;
+use shopping_list::ShoppingList;
+use unit_price::{PackSize, Unit};
+use wishlist::Wishlists;
+
+#[derive(Debug, Clone)]
 struct SupermarketItem {
     name: String,
     price: f64,
+    quantity: u32,
+    weight_kg: f64,
+    dimensions_cm: [f64; 3],
+    min_age: Option<u32>,
+    category: Option<String>,
+    aisle: Option<u32>,
+    pack: Option<PackSize>,
+    // Per 100 g.
+    nutrition: Option<Nutrition>,
+    allergens: Vec<Allergen>,
+    diet: Vec<DietTag>,
+    // The promotions the line is on, see promotions.rs.
+    price_cut: Option<PriceCut>,
+    multi_buy: Option<MultiBuy>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 struct ShoppingCart {
     item: Vec<SupermarketItem>,
+    // The age an ID check has confirmed, see `verify_age`.
+    verified_age: Option<u32>,
+}
+
+impl SupermarketItem {
+    // A single, unrestricted item with no weight, size or category. Use
+    // SupermarketItem::builder to set anything else.
+    fn new(name: &str, price: f64) -> Result<SupermarketItem, ItemError> {
+        SupermarketItem::builder(name, price).build()
+    }
+
+    fn validate(&self) -> Result<(), ItemError> {
+        if self.name.trim().is_empty() {
+            return Err(ItemError::EmptyName);
+        }
+        if !self.price.is_finite() || self.price < 0.0 {
+            return Err(ItemError::InvalidPrice(self.price));
+        }
+        if self.quantity == 0 {
+            return Err(ItemError::ZeroQuantity);
+        }
+        if !self.weight_kg.is_finite() || self.weight_kg < 0.0 {
+            return Err(ItemError::InvalidWeight(self.weight_kg));
+        }
+        if self
+            .dimensions_cm
+            .iter()
+            .any(|side| !side.is_finite() || *side < 0.0)
+        {
+            return Err(ItemError::InvalidDimensions(self.dimensions_cm));
+        }
+        if self
+            .category
+            .as_ref()
+            .is_some_and(|category| category.trim().is_empty())
+        {
+            return Err(ItemError::EmptyCategory);
+        }
+        if let Some(facts) = self.nutrition {
+            let values = [
+                facts.kcal,
+                facts.fat_g,
+                facts.sugar_g,
+                facts.protein_g,
+                facts.salt_g,
+            ];
+            if values
+                .iter()
+                .any(|value| !value.is_finite() || *value < 0.0)
+            {
+                return Err(ItemError::InvalidNutrition);
+            }
+        }
+        if let Some(pack) = self.pack
+            && (!pack.amount.is_finite() || pack.amount <= 0.0)
+        {
+            return Err(ItemError::InvalidPackSize(pack.amount));
+        }
+        if let Some(cut) = &self.price_cut
+            && (!cut.list_price.is_finite() || cut.list_price < 0.0)
+        {
+            return Err(ItemError::InvalidListPrice(cut.list_price));
+        }
+        if let Some(deal) = &self.multi_buy
+            && !MultiBuy::is_valid(deal.buy, deal.pay)
+        {
+            return Err(ItemError::InvalidMultiBuy {
+                buy: deal.buy,
+                pay: deal.pay,
+            });
+        }
+        Ok(())
+    }
 }
 
 impl ShoppingCart {
-    fn traverse_items<F>(&mut self, mut operation: F)
+    fn new(item: Vec<SupermarketItem>) -> Result<ShoppingCart, CartError> {
+        for (line, entry) in item.iter().enumerate() {
+            entry
+                .validate()
+                .map_err(|error| CartError::Item { line, error })?;
+        }
+        Ok(ShoppingCart {
+            item,
+            verified_age: None,
+        })
+    }
+
+    // Every item is checked again after the closure has run on it. If the
+    // closure left one invalid, the traversal stops and the whole cart is
+    // put back the way it was, so a failed traversal changes nothing.
+    fn traverse_items<F>(&mut self, mut operation: F) -> Result<(), CartError>
     where
         F: FnMut(&mut SupermarketItem),
     {
+        let before = self.item.clone();
         let mut start_index = 0;
 
         while start_index < self.item.len() {
             operation(&mut self.item[start_index]);
+            if let Err(error) = self.item[start_index].validate() {
+                self.item = before;
+                return Err(CartError::Item {
+                    line: start_index,
+                    error,
+                });
+            }
             start_index += 1
         }
+        Ok(())
     }
 
-    fn checkout<F>(self, operation: F)
+    // Refuses a cart with an age-restricted line nobody has verified.
+    fn checkout<F>(self, operation: F) -> Result<(), AgeError>
     where
         F: FnOnce(ShoppingCart),
     {
-        operation(self)
+        if let Some(item) = self.unverified_line() {
+            return Err(AgeError::NotVerified(item.name.clone()));
+        }
+        operation(self);
+        Ok(())
+    }
+
+    // After promotions, before tax.
+    fn total(&self) -> f64 {
+        round_cents(self.item.iter().map(SupermarketItem::line_total).sum())
     }
 }
 
-fn main() {
-    let mut items = ShoppingCart {
-        item: vec![
-            SupermarketItem {
-                name: String::from("APPLE"),
-                price: 3.99,
-            },
-            SupermarketItem {
-                name: "BANANA".to_string(),
-                price: 2.99,
-            },
-        ],
-    };
+// The small catalog the demo below shops from.
+fn demo_catalog() -> Result<Catalog, CartError> {
+    let mut catalog = Catalog::new();
+    catalog.add(
+        "1001",
+        SupermarketItem::builder("MILK 4 PINT", 3.49)
+            .category("dairy")
+            .pack(4.0, Unit::Pint)
+            .build()?,
+    )?;
+    catalog.add(
+        "1002",
+        SupermarketItem::builder("OAT DRINK", 2.10)
+            .category("dairy")
+            .pack(1.0, Unit::Litre)
+            .build()?,
+    )?;
+    catalog.add("1003", SupermarketItem::new("CHOCOLATE", 1.49)?)?;
+    catalog.add("1004", SupermarketItem::new("SODA", 0.99)?)?;
+    catalog.add("1005", SupermarketItem::new("DELI HAM", 0.0)?)?;
+    // Loose fruit is sold by weight, at these prices per kg.
+    catalog.add("1006", SupermarketItem::new("LOOSE APPLES", 2.40)?)?;
+    catalog.add("1007", SupermarketItem::new("LOOSE BANANAS", 1.20)?)?;
+    catalog.add(
+        "1008",
+        SupermarketItem::builder("APPLES 6 PACK", 2.99)
+            .pack(6.0, Unit::Each)
+            .build()?,
+    )?;
+    catalog.add_barcode("5012345678900", "1001")?;
+    catalog.add_barcode("036000291452", "1003")?;
+    catalog.add_barcode("96385074", "1004")?;
+    catalog.add_barcode("2012345004993", "1005")?;
+    catalog.add_barcode("2712340012503", "1006")?;
+    Ok(catalog)
+}
 
-    items.traverse_items(|item| item.price *= 0.85);
+// The headers our buyers' pricing spreadsheet uses.
+fn buyer_mapping() -> ColumnMapping {
+    ColumnMapping::new()
+        .column(Column::Sku, "Item Code")
+        .column(Column::Name, "Description")
+        .column(Column::Price, "Unit Price")
+        .column(Column::Category, "Dept")
+        .column(Column::Allergens, "Allergens")
+        .column(Column::Diet, "Diet")
+}
 
-    items.traverse_items(|items_name| {
-        items_name.name = items_name.name.to_lowercase();
-    });
+fn main() -> Result<(), CartError> {
+    // `cargo run -- import shopping_list.txt` turns a typed-up list into a
+    // cart, and `cargo run -- prices price_list.csv` shows what a buyer's
+    // price list would change, instead of running the demo.
+    let args: Vec<String> = env::args().collect();
+    if let [_, command, path] = args.as_slice()
+        && (command == "import" || command == "prices")
+    {
+        let catalog = demo_catalog()?;
+        let read_failed = |error: std::io::Error| -> ! {
+            eprintln!("couldn't read {}: {}", path, error);
+            std::process::exit(1);
+        };
+        if command == "import" {
+            let list = ShoppingList::load(path).unwrap_or_else(|error| read_failed(error));
+            println!(
+                "{}",
+                list.import(&catalog, &SearchIndex::from_catalog(&catalog))
+            );
+        } else {
+            let prices =
+                PriceList::load(path, &buyer_mapping()).unwrap_or_else(|error| read_failed(error));
+            for error in &prices.errors {
+                println!("{}", error);
+            }
+            println!("{}", catalog.diff_prices(&prices));
+        }
+        return Ok(());
+    }
+
+    // The demo runs on a clock frozen at start-up, so every step below sees
+    // the same moment.
+    let clock = FakeClock::new(SystemClock.now());
+    let now = clock.now();
+    let last_month = now - 30 * 86_400;
+
+    let mut items = cart!["APPLE" => 3.99, "BANANA" => 2.99];
+
+    // cart! checks its literals at compile time; SupermarketItem::new checks
+    // values that are only known at runtime.
+    if let Err(error) = SupermarketItem::new("", f64::NAN) {
+        println!("Rejected: {}", error);
+    }
+
+    let mut history = PriceHistory::new();
+    history.record("APPLE", 3.49, last_month, PriceSource::CatalogUpdate);
+    history.record(
+        "APPLE",
+        3.99,
+        last_month + 10 * 86_400,
+        PriceSource::CatalogUpdate,
+    );
+    history.record("BANANA", 2.99, last_month, PriceSource::CatalogUpdate);
+
+    // The 15% discount goes through `reprice`, so the history keeps the
+    // old prices as well as the new ones.
+    items.reprice(&mut history, PriceSource::Promotion, &clock, |item| {
+        item.price = round_cents(item.price * 0.85)
+    })?;
+    for product in ["APPLE", "BANANA"] {
+        for change in history.history(product) {
+            println!(
+                "{} {:.2} from {} ({:?})",
+                product, change.price, change.effective_from, change.source
+            );
+        }
+        if let Some(report) = history.report(product, last_month, now + 1) {
+            let change = report
+                .change_percent
+                .map_or("n/a".to_string(), |percent| format!("{:+.1}%", percent));
+            println!(
+                "{}: avg {:.2}, min {:.2}, max {:.2}, {}",
+                product, report.average, report.min, report.max, change
+            );
+        }
+    }
+    history.record("APPLE", 2.99, now + 60, PriceSource::ManualOverride);
+    println!(
+        "APPLE: {:?} now, {:?} after the manual override",
+        history.price_at("APPLE", now),
+        history.price_at("APPLE", now + 60)
+    );
+
+    // A closure that would leave a negative price is rolled back.
+    if let Err(error) = items.traverse_items(|item| item.price -= 100.0) {
+        println!("Rejected: {}", error);
+    }
 
     let mut total_price = 0.0;
+    let locale = Locale::from_env();
 
     items.checkout(|mut cart| {
         println!("{:?}", cart);
 
-        cart.traverse_items(|items| total_price += items.price);
-        println!("{:.2}", total_price)
+        cart.traverse_items(|items| total_price += items.price * items.quantity as f64)
+            .expect("adding up prices leaves every item valid");
+        println!("{}", locale.format_money(total_price, "USD"))
+    })?;
+
+    // A store of live carts, driven by a hand-wound clock so the demo can
+    // fast-forward past the idle timeout.
+    let store_clock = FakeClock::new(now);
+    let mut abandoned = 0;
+    {
+        let mut store = CartStore::new(
+            30 * 60,
+            || store_clock.now(),
+            |id: &str, cart| {
+                if abandoned == 0 {
+                    println!(
+                        "Reminder to {}: you left {} items behind",
+                        id,
+                        cart.item.len()
+                    );
+                }
+                abandoned += 1;
+            },
+        );
+        for session in 0..20_000 {
+            store.insert(&format!("session-{}", session), cart!["APPLE" => 4 x 2]);
+        }
+        store_clock.advance(20 * 60);
+        if let Some(cart) = store.get_mut("session-7") {
+            cart.traverse_items(|item| item.quantity += 1)?;
+        }
+        store_clock.advance(20 * 60);
+        store.expire_idle();
+        let kept = store
+            .remove("session-7")
+            .map_or(0, |cart| cart.item[0].quantity);
+        println!(
+            "{} live carts left, session-7 kept with {} apples",
+            store.len(),
+            kept
+        );
+        println!("store empty: {}", store.is_empty());
+    }
+    println!("{} carts abandoned", abandoned);
+
+    let mut ledger = Ledger::new();
+    let mut gift_card = GiftCard::issue("GC-1001", 5.00, "USD", now + 86_400, &mut ledger, &clock)?;
+    let mut store_credit = StoreCredit::new("customer-42");
+    store_credit.credit(2.50, "goodwill", &mut ledger, &clock)?;
+    if let Err(error) = store_credit.credit(f64::NAN, "typo", &mut ledger, &clock) {
+        println!("Rejected credit: {:?}", error);
+    }
+
+    let mut cart = ShoppingCart::builder()
+        .item("MILK", 4.49)
+        .qty(3)
+        .category("dairy")
+        .pack(1.0, Unit::Litre)
+        .weight(1.05)
+        .dimensions([10.0, 10.0, 25.0])
+        .item("BREAD", 3.25)
+        .category("bakery")
+        .weight(0.8)
+        .dimensions([30.0, 12.0, 12.0])
+        .item("BEER", 1.99)
+        .category("alcohol")
+        .weight(0.5)
+        .dimensions([6.5, 6.5, 16.0])
+        .min_age(21)
+        .build()?;
+
+    let catalog = demo_catalog()?;
+    for alternative in cart.cheaper_alternatives(&catalog) {
+        println!(
+            "{} costs {}; {} ({}) is {} ({:.0}% less)",
+            alternative.line,
+            alternative.line_price,
+            alternative.name,
+            alternative.sku,
+            alternative.unit_price,
+            alternative.saving_percent
+        );
+    }
+
+    let mut shopping_list = ShoppingCart::builder()
+        .item("TEA", 2.99)
+        .category("drinks")
+        .item("CHEDDAR", 3.49)
+        .category("dairy")
+        .item("BAGELS", 1.99)
+        .category("bakery")
+        .aisle(1)
+        .item("YOGHURT", 0.89)
+        .category("dairy")
+        .item("BATTERIES", 4.99)
+        .build()?;
+    for (category, items) in shopping_list.group_by(|item| item.category.clone()) {
+        let names: Vec<&str> = items.iter().map(|item| item.name.as_str()).collect();
+        println!("{:?}: {:?}", category, names);
+    }
+    shopping_list.sort_by_category();
+    shopping_list.sort_by_aisle();
+
+    let mut layout = StoreLayout::new(vec![1, 4, 7, 2]);
+    layout.place("drinks", 2);
+    layout.place("dairy", 7);
+    shopping_list.sort_for_walk(&layout);
+    let walk: Vec<String> = shopping_list
+        .item
+        .iter()
+        .map(|item| format!("{} ({:?})", item.name, layout.aisle_of(item)))
+        .collect();
+    println!("walking order: {}", walk.join(", "));
+
+    // The till scans barcodes and looks the products up in the catalog.
+    let mut till = ShoppingCart::new(Vec::new())?;
+    till.add_by_sku("1002", &catalog)?;
+    for code in [
+        "5012345678900",
+        "0036000291452",
+        "9638 5074",
+        "2012345007994",
+        "2712340012503",
+        "5012345678901",
+    ] {
+        if let Err(error) = till.scan(code, &catalog) {
+            println!("Scan {} failed: {}", code, error);
+        }
+    }
+    for item in &till.item {
+        println!(
+            "scanned {} {:.2} ({} kg)",
+            item.name, item.price, item.weight_kg
+        );
+    }
+
+    // Staff type half a name, or a misspelt one, into the till.
+    let mut index = SearchIndex::from_catalog(&catalog);
+    index.add_alias("1004", "cola");
+    for query in ["chocolat", "aple", "milk pint", "cola"] {
+        let hits = index.search(query, 3);
+        let found: Vec<String> = hits
+            .iter()
+            .map(|hit| format!("{} {} ({})", hit.key, hit.name, hit.score))
+            .collect();
+        println!("{:?} -> {:?}", query, found);
+        if let Some(best) = hits.first() {
+            till.add_by_sku(&best.key, &catalog)?;
+        }
+    }
+    let in_till = SearchIndex::from_cart(&till).search("ham", 1);
+    println!("ham is on line {:?}", in_till.first().map(|hit| &hit.key));
+
+    // A typed-up shopping list goes through the same index, aliases and all.
+    let list = ShoppingList::parse("2x apples\n1.5 kg bananas\nmilk 2L\ncola x2\nbread");
+    for line in &list.lines {
+        println!(
+            "list line {}: {:?} {:?}",
+            line.line, line.amount, line.query
+        );
+    }
+    let imported = list.import(&catalog, &index);
+    println!("{}", imported);
+
+    // Buyers reprice from a spreadsheet. Bad rows are reported by line and
+    // skipped; the dry run shows what would change before anything does.
+    let prices = PriceList::load("price_list.csv", &buyer_mapping()).unwrap_or_default();
+    for error in &prices.errors {
+        println!("price list {}", error);
+    }
+    let mut buying = demo_catalog()?;
+    println!("dry run:\n{}", buying.diff_prices(&prices));
+    let applied = buying.apply_prices(&prices, &mut history, &clock);
+    println!(
+        "applied {} price changes; chocolate now {:?}",
+        applied.changed.len(),
+        history.price_at("CHOCOLATE", now)
+    );
+    // The old price was recorded too, so the change can be reported.
+    if let Some(report) = history.report("CHOCOLATE", last_month, now + 1)
+        && let Some(change) = report.change_percent
+    {
+        println!("chocolate changed {:+.1}%", change);
+    }
+    println!("{}", buying.to_csv(&buyer_mapping()));
+    println!("{}", imported.cart.to_csv(&ColumnMapping::default()));
+    println!("as a cart: {:.2}", prices.to_cart()?.total());
+
+    // Time-based promotions, tried on a fake clock: a Friday evening gets
+    // the happy hour, later on the end-of-day markdown, and Saturday
+    // morning the weekend deal.
+    let mut promotions = Promotions::new(0);
+    promotions.add(
+        "happy hour",
+        Schedule::HappyHour {
+            from: 17 * 60,
+            to: 19 * 60,
+        },
+        20.0,
+        |item| item.category.as_deref() == Some("dairy"),
+    )?;
+    promotions.add("weekend deal", Schedule::Weekend, 10.0, |_| true)?;
+    promotions.add(
+        "end of day",
+        Schedule::EndOfDay { from: 20 * 60 },
+        50.0,
+        |item| item.name.starts_with("LOOSE"),
+    )?;
+    if let Err(error) = promotions.add("typo", Schedule::Always, 150.0, |_| true) {
+        println!("Rejected promotion: {:?}", error);
+    }
+    // Friday 5 January 2024, 17:30 UTC.
+    let shop_clock = FakeClock::new(1_704_475_800);
+    let mut promotion_history = PriceHistory::new();
+    for hours_later in [0, 3, 16] {
+        shop_clock.set(1_704_475_800 + hours_later * 3_600);
+        let mut basket = imported.cart.clone();
+        let applied = basket.apply_promotions(&promotions, &mut promotion_history, &shop_clock)?;
+        let time = LocalTime::at(shop_clock.now(), 0);
+        println!(
+            "{:?} {:02}:{:02}, basket {:.2}",
+            time.weekday,
+            time.minute_of_day / 60,
+            time.minute_of_day % 60,
+            basket.total()
+        );
+        for promotion in applied {
+            println!("  {}", promotion);
+        }
+    }
+
+    let mut big_catalog = Catalog::new();
+    let words = [
+        "ORGANIC", "APPLE", "BANANA", "MILK", "BREAD", "CHEESE", "JUICE", "TEA",
+    ];
+    for number in 0..100_000 {
+        let name = format!(
+            "{} {} {}",
+            words[number % words.len()],
+            words[(number / words.len()) % words.len()],
+            number
+        );
+        big_catalog.add(&number.to_string(), SupermarketItem::new(&name, 1.0)?)?;
+    }
+    let big_index = SearchIndex::from_catalog(&big_catalog);
+    let started = Instant::now();
+    let hits = big_index.search("chese banan", 5);
+    println!(
+        "{} hits in 100k products in {:?}, best {:?}",
+        hits.len(),
+        started.elapsed(),
+        hits.first().map(|hit| &hit.name)
+    );
+
+    // A self-checkout lane with a simulated bagging-area scale.
+    let bagging_area = Cell::new(0.0);
+    let mut lane = SelfCheckout::start(ShoppingCart::new(Vec::new())?, || bagging_area.get(), 0.05);
+    lane.scan("2712340012503", &catalog)?;
+    bagging_area.set(1.25);
+    lane.check_weight();
+    // The shopper puts their own bag down too.
+    bagging_area.set(1.25 + 0.4);
+    if let Some(intervention) = lane.check_weight() {
+        println!("Please wait for assistance: {:?}", intervention);
+    }
+    let cleared = lane.attendant_override(|intervention| {
+        println!("Attendant checks {:?} ... own bag, approved", intervention);
+        true
+    });
+    println!("lane clear: {}", cleared);
+    // The soda has no weight on file, so the scale can't check it.
+    lane.scan("96385074", &catalog)?;
+    bagging_area.set(1.25 + 0.4 + 0.35);
+    if let Some(intervention) = lane.check_weight() {
+        println!("Please wait for assistance: {:?}", intervention);
+    }
+    lane.attendant_override(|intervention| {
+        println!("Attendant checks {:?} ... bagged, approved", intervention);
+        true
     });
+    lane.finish()?
+        .checkout(|cart| println!("self-checkout paid for {} lines", cart.item.len()))?;
+
+    // Nobody checked an ID for the wine, so checkout won't take it.
+    let wine = ShoppingCart::builder()
+        .item("WINE", 8.99)
+        .min_age(18)
+        .build()?;
+    if let Err(AgeError::NotVerified(name)) = wine.checkout(|_| ()) {
+        println!("Checkout refused: {} needs an ID check", name);
+    }
+
+    let mut snacks = ShoppingCart::builder()
+        .item("PEANUT BAR", 1.20)
+        .qty(2)
+        .pack(45.0, Unit::Gram)
+        .nutrition(Nutrition {
+            kcal: 520.0,
+            fat_g: 30.0,
+            sugar_g: 28.0,
+            protein_g: 18.0,
+            salt_g: 0.4,
+        })
+        .allergen(Allergen::Peanuts)
+        .diet(DietTag::Vegetarian)
+        .item("APPLE JUICE", 1.80)
+        .pack(1.0, Unit::Litre)
+        .nutrition(Nutrition {
+            kcal: 46.0,
+            sugar_g: 10.0,
+            ..Nutrition::default()
+        })
+        .diet(DietTag::Vegan)
+        .diet(DietTag::Vegetarian)
+        .build()?;
+    for item in snacks.containing(Allergen::Peanuts) {
+        println!("Warning: {} contains peanuts", item.name);
+    }
+    let facts = snacks.nutrition_totals();
+    println!(
+        "{:.0} kcal, {:.0} g sugar, {:.0} g fat, {:.0} g protein, {:.1} g salt ({} lines unknown)",
+        facts.totals.kcal,
+        facts.totals.sugar_g,
+        facts.totals.fat_g,
+        facts.totals.protein_g,
+        facts.totals.salt_g,
+        facts.unknown_lines
+    );
+    for item in snacks.retain_by_diet(&[DietTag::Vegan]) {
+        println!("Not vegan, removed: {}", item.name);
+    }
+
+    let names = cart![
+        "Straße Mix" => 2.49,
+        " STRASSE  mix" => 2.49,
+        "Café Crème" => 3.10,
+        "CAFE CREME" => 3.10,
+        "ﬁg rolls" => 1.20,
+    ];
+    println!(
+        "{} normalises to {:?}",
+        names.item[4].name,
+        names.item[4].normalized_name()
+    );
+    println!(
+        "duplicates: {:?}",
+        names.duplicates(NormalizeOptions::default())
+    );
+    println!(
+        "ignoring accents: {:?}",
+        names.duplicates(NormalizeOptions {
+            strip_accents: true
+        })
+    );
+
+    // A support agent edits the cart; the diff shows exactly what changed.
+    let before_edit = cart.clone();
+    cart.traverse_items(|item| {
+        if item.name == "MILK" {
+            item.price = 3.99;
+        }
+    })?;
+    let mut lines = cart.item.clone();
+    lines.push(SupermarketItem::new("EGGS", 2.49)?);
+    let mut edited = ShoppingCart::new(lines)?;
+    edited.traverse_items(|item| {
+        if item.name == "BREAD" {
+            item.quantity = 2;
+        }
+    })?;
+    println!("{}", before_edit.diff(&cart));
+    println!("{}", cart.diff(&edited));
+    println!("{}", cart.diff(&cart));
+
+    // The bread is saved for the weekend, then comes back when it's cheaper.
+    let mut wishlists = Wishlists::new("customer-42");
+    cart.move_to_wishlist(1, wishlists.list("weekend"))?;
+    // The lookup is given normalised names.
+    let todays_prices = |name: &str| if name == "bread" { Some(2.75) } else { None };
+    for drop in wishlists.price_drops(todays_prices) {
+        println!(
+            "{} on {}'s {} list dropped from {:.2} to {:.2}",
+            drop.name, wishlists.customer_id, drop.list, drop.saved_price, drop.current_price
+        );
+    }
+    cart.move_to_cart(0, wishlists.list("weekend"))?;
+
+    // Checkout refuses the beer unless someone has checked an ID first.
+    let verified = cart.verify_age(|age| {
+        println!("ID check: customer must be {}+ ... OK", age);
+        true
+    });
+    if let Err(AgeError::Refused(refused)) = verified {
+        println!("Refused {} age-restricted items", refused.len());
+    }
+
+    let mut archive = ReceiptArchive::load("receipt_archive.txt").unwrap_or_else(|error| {
+        println!("No receipt archive ({}), starting empty", error);
+        ReceiptArchive::default()
+    });
+    let stats = archive.mine();
+    println!(
+        "MILK + BREAD bought together {} times",
+        stats.pair_count("MILK", "BREAD")
+    );
+    for suggestion in stats.recommend(&cart, 3) {
+        println!(
+            "Add {}? (with {}: support {:.2}, confidence {:.2}, lift {:.2})",
+            suggestion.product,
+            suggestion.because_of,
+            suggestion.support,
+            suggestion.confidence,
+            suggestion.lift
+        );
+    }
+
+    let config = ShippingConfig::load("shipping_rates.conf")?;
+    let quote = cart.quote_shipping(&config, "domestic")?;
+    for option in &quote.options {
+        println!("{:?}", option);
+    }
+
+    // The scheduler runs on the demo's clock too; checkout_with_delivery
+    // stamps the receipt with it.
+    let mut scheduler = SlotScheduler::new(15 * 60, || clock.now());
+    let tomorrow_morning = now + 86_400;
+    for hour in [9, 11, 13] {
+        scheduler.add_slot(Slot {
+            starts_at: tomorrow_morning + (hour - 9) * 3_600,
+            ends_at: tomorrow_morning + (hour - 7) * 3_600,
+            capacity: 2,
+            cutoff: now + 12 * 3_600,
+        });
+    }
+    for (index, slot, free) in scheduler.available() {
+        println!(
+            "slot {}: {}..{} ({} free)",
+            index, slot.starts_at, slot.ends_at, free
+        );
+    }
+    // The shopper changes their mind about the first slot they picked.
+    let first_choice = scheduler.hold(2, "customer-42")?;
+    scheduler.release(first_choice);
+    let hold = scheduler.hold(0, "customer-42")?;
+
+    // Milk is on a 3 for 2. Groceries are tax-free; everything else pays 8%
+    // and earns a point per dollar.
+    let mut till_deals = Promotions::new(0);
+    till_deals.add_multi_buy("MILK 3 FOR 2", Schedule::Always, 3, 2, |item| {
+        item.name == "MILK"
+    })?;
+    for deal in cart.apply_promotions(&till_deals, &mut history, &clock)? {
+        println!("{}", deal);
+    }
+    let policy = StorePolicy::new(8.0, 1)?
+        .tax_category("dairy", 0.0)?
+        .tax_category("bakery", 0.0)?;
+    let mut loyalty = LoyaltyAccount::new("customer-42");
+
+    let tenders = vec![
+        Tender::GiftCard(&mut gift_card),
+        Tender::StoreCredit(&mut store_credit),
+    ];
+    let (mut receipt, booking) = cart.checkout_with_delivery(
+        &mut scheduler,
+        hold,
+        &policy,
+        Some(quote.cheapest),
+        tenders,
+        &mut ledger,
+    )?;
+    println!("Booked slot {} for {}", booking.slot, booking.session);
+    loyalty.earn(&receipt);
+    println!("{}", receipt.render(locale));
+    for tag in ["de-DE", "fr-FR", "ja-JP"] {
+        if let Some(other) = Locale::get(tag) {
+            println!(
+                "{}: {} | {} | {} | {}",
+                other.tag,
+                other.format_money(receipt.total, STORE_CURRENCY),
+                other.format_money(1234.5, "JPY"),
+                other.format_quantity(1.25, 2, "kg"),
+                other.format_date(receipt.issued_at)
+            );
+        }
+    }
+
+    let splits = [
+        receipt.split_evenly(&["ana", "ben", "cleo"]),
+        receipt.split_by_percentage(&[("ana", 50.0), ("ben", 30.0), ("cleo", 20.0)]),
+        receipt.split_by_lines(&["ana", "ben", "ben"]),
+        receipt.split_by(|line| if line.name == "MILK" { "ben" } else { "cleo" }.to_string()),
+    ];
+    for split in splits {
+        split?.iter().for_each(|share| println!("{}", share));
+    }
+    if let Err(error) = receipt.split_by_percentage(&[("ana", f64::NAN), ("ben", 100.0)]) {
+        println!("Rejected split: {:?}", error);
+    }
+
+    archive.add(&receipt);
+    if let Err(error) = archive.save(env::temp_dir().join("receipt_archive.txt")) {
+        println!("Couldn't save the receipt archive: {}", error);
+    }
+    println!("{} delivery bookings", scheduler.bookings().len());
+
+    // One of the three bottles of milk comes back, which breaks the 3 for 2,
+    // and so does the beer; refund them as store credit.
+    let beer = receipt
+        .lines
+        .iter()
+        .position(|line| line.name == "BEER")
+        .unwrap_or(0);
+    let refund = receipt.process_return(&[(0, 1), (beer, 1)], &clock, DEFAULT_RETURN_WINDOW)?;
+    println!("{}", refund.render(locale));
+    loyalty.reverse(&refund);
+    println!(
+        "{} has {} points ({} left on the receipt)",
+        loyalty.customer_id, loyalty.points, receipt.points
+    );
+    store_credit.credit(refund.total, "refund", &mut ledger, &clock)?;
+
+    for entry in ledger.entries() {
+        println!("{}", entry);
+    }
+    println!(
+        "{} reconciles to {:.2} (live {:.2})",
+        gift_card.code,
+        ledger.reconcile(&gift_card.account()),
+        gift_card.balance
+    );
+    Ok(())
 }
@@ -0,0 +1,5 @@
+// Money is kept as f64 throughout the cart, so anything that ends up on a
+// receipt or in the ledger is rounded to whole cents first.
+pub fn round_cents(amount: f64) -> f64 {
+    (amount * 100.0).round() / 100.0
+}
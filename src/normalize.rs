@@ -0,0 +1,83 @@
+// Unicode-aware product name normalisation.
+// Names are compared in a normalised form while the name shown to the
+// shopper is left exactly as it was entered. Normalising means:
+//
+//   1. NFKC, so compatibility forms ("ﬁ", full-width letters, "½") and
+//      composed/decomposed accents all end up the same
+//   2. case folding: lowercase, plus the folds lowercasing misses, so that
+//      "Straße" and "STRASSE" compare equal
+//   3. collapsing runs of whitespace into one space and trimming the ends
+//   4. optionally, stripping accents ("Café" -> "cafe")
+
+use unicode_normalization::UnicodeNormalization;
+use unicode_normalization::char::is_combining_mark;
+
+use crate::{ShoppingCart, SupermarketItem};
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NormalizeOptions {
+    pub strip_accents: bool,
+}
+
+// Full case folding for the characters whose fold differs from their
+// lowercase form and that NFKC doesn't already take care of.
+fn fold_case(text: &str) -> String {
+    let mut folded = String::with_capacity(text.len());
+    for ch in text.chars() {
+        match ch {
+            'ß' | 'ẞ' => folded.push_str("ss"),
+            'ς' => folded.push('σ'),
+            'İ' => folded.push_str("i\u{307}"),
+            _ => folded.extend(ch.to_lowercase()),
+        }
+    }
+    folded
+}
+
+pub fn normalize_name(name: &str, options: NormalizeOptions) -> String {
+    let folded: String = fold_case(&name.nfkc().collect::<String>()).nfkc().collect();
+    let collapsed = folded.split_whitespace().collect::<Vec<_>>().join(" ");
+
+    if options.strip_accents {
+        collapsed
+            .nfd()
+            .filter(|ch| !is_combining_mark(*ch))
+            .nfc()
+            .collect()
+    } else {
+        collapsed
+    }
+}
+
+impl SupermarketItem {
+    // The name used for comparisons; `name` itself is what gets displayed.
+    pub fn normalized_name(&self) -> String {
+        normalize_name(&self.name, NormalizeOptions::default())
+    }
+
+    pub fn same_product(&self, other: &SupermarketItem) -> bool {
+        self.normalized_name() == other.normalized_name()
+    }
+}
+
+impl ShoppingCart {
+    // Groups of line indexes whose names normalise to the same thing, e.g.
+    // "Straße Mix" and " STRASSE  mix".
+    pub fn duplicates(&self, options: NormalizeOptions) -> Vec<Vec<usize>> {
+        let mut groups: Vec<(String, Vec<usize>)> = Vec::new();
+
+        for (line, item) in self.item.iter().enumerate() {
+            let key = normalize_name(&item.name, options);
+            match groups.iter_mut().find(|(existing, _)| *existing == key) {
+                Some((_, lines)) => lines.push(line),
+                None => groups.push((key, vec![line])),
+            }
+        }
+
+        groups
+            .into_iter()
+            .map(|(_, lines)| lines)
+            .filter(|lines| lines.len() > 1)
+            .collect()
+    }
+}
@@ -0,0 +1,163 @@
+// Nutrition facts, allergens and dietary tags.
+// Nutrition is declared per 100 g (or 100 ml, taken as 100 g). Cart totals
+// scale it by how much of each item there is: its pack size if it has one,
+// otherwise its shipping weight.
+//
+// Allergens and dietary tags parse from, and print as, the plain names a
+// price list spreadsheet uses ("tree nuts", "gluten-free").
+
+use std::fmt;
+use std::str::FromStr;
+
+use crate::unit_price::Measure;
+use crate::{ShoppingCart, SupermarketItem};
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Nutrition {
+    pub kcal: f64,
+    pub fat_g: f64,
+    pub sugar_g: f64,
+    pub protein_g: f64,
+    pub salt_g: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Allergen {
+    Peanuts,
+    TreeNuts,
+    Milk,
+    Eggs,
+    Gluten,
+    Soy,
+    Fish,
+    Shellfish,
+    Sesame,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DietTag {
+    Vegan,
+    Vegetarian,
+    GlutenFree,
+    Halal,
+    Kosher,
+}
+
+#[derive(Debug, Default)]
+pub struct NutritionTotals {
+    pub totals: Nutrition,
+    // Lines left out because they have no nutrition facts or no known amount.
+    pub unknown_lines: usize,
+}
+
+impl FromStr for Allergen {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Allergen, String> {
+        match text.trim().to_lowercase().as_str() {
+            "peanut" | "peanuts" => Ok(Allergen::Peanuts),
+            "tree nuts" | "nuts" => Ok(Allergen::TreeNuts),
+            "milk" | "dairy" => Ok(Allergen::Milk),
+            "egg" | "eggs" => Ok(Allergen::Eggs),
+            "gluten" => Ok(Allergen::Gluten),
+            "soy" | "soya" => Ok(Allergen::Soy),
+            "fish" => Ok(Allergen::Fish),
+            "shellfish" | "crustaceans" => Ok(Allergen::Shellfish),
+            "sesame" => Ok(Allergen::Sesame),
+            other => Err(format!("unknown allergen `{}`", other)),
+        }
+    }
+}
+
+impl FromStr for DietTag {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<DietTag, String> {
+        match text.trim().to_lowercase().as_str() {
+            "vegan" => Ok(DietTag::Vegan),
+            "vegetarian" => Ok(DietTag::Vegetarian),
+            "gluten-free" | "gluten free" => Ok(DietTag::GlutenFree),
+            "halal" => Ok(DietTag::Halal),
+            "kosher" => Ok(DietTag::Kosher),
+            other => Err(format!("unknown dietary tag `{}`", other)),
+        }
+    }
+}
+
+impl fmt::Display for Allergen {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Allergen::Peanuts => "peanuts",
+            Allergen::TreeNuts => "tree nuts",
+            Allergen::Milk => "milk",
+            Allergen::Eggs => "eggs",
+            Allergen::Gluten => "gluten",
+            Allergen::Soy => "soy",
+            Allergen::Fish => "fish",
+            Allergen::Shellfish => "shellfish",
+            Allergen::Sesame => "sesame",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl fmt::Display for DietTag {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            DietTag::Vegan => "vegan",
+            DietTag::Vegetarian => "vegetarian",
+            DietTag::GlutenFree => "gluten-free",
+            DietTag::Halal => "halal",
+            DietTag::Kosher => "kosher",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl SupermarketItem {
+    // Grams in one unit of this item, if we know.
+    fn grams(&self) -> Option<f64> {
+        match self.pack.map(|pack| pack.base()) {
+            Some((Measure::Mass | Measure::Volume, amount)) => Some(amount),
+            _ if self.weight_kg > 0.0 => Some(self.weight_kg * 1000.0),
+            _ => None,
+        }
+    }
+}
+
+impl ShoppingCart {
+    // Every line that declares `allergen`, e.g. to warn about peanuts.
+    pub fn containing(&self, allergen: Allergen) -> Vec<&SupermarketItem> {
+        self.item
+            .iter()
+            .filter(|item| item.allergens.contains(&allergen))
+            .collect()
+    }
+
+    pub fn nutrition_totals(&self) -> NutritionTotals {
+        let mut result = NutritionTotals::default();
+
+        for item in &self.item {
+            let (Some(per_100g), Some(grams)) = (item.nutrition, item.grams()) else {
+                result.unknown_lines += 1;
+                continue;
+            };
+            let scale = grams / 100.0 * item.quantity as f64;
+            let totals = &mut result.totals;
+            totals.kcal += per_100g.kcal * scale;
+            totals.fat_g += per_100g.fat_g * scale;
+            totals.sugar_g += per_100g.sugar_g * scale;
+            totals.protein_g += per_100g.protein_g * scale;
+            totals.salt_g += per_100g.salt_g * scale;
+        }
+        result
+    }
+
+    // Keeps only the items tagged with every one of `diet` and hands back
+    // the ones that were taken out.
+    pub fn retain_by_diet(&mut self, diet: &[DietTag]) -> Vec<SupermarketItem> {
+        self.item
+            .extract_if(.., |item| !diet.iter().all(|tag| item.diet.contains(tag)))
+            .collect()
+    }
+}
@@ -0,0 +1,99 @@
+// What the store adds on top of the cart at checkout: sales tax, by
+// category, and loyalty points for what the customer spent.
+// Prices are shown before tax; each line is taxed on its total after
+// promotions. Points are earned per whole unit of currency spent on goods,
+// after promotions, not counting tax or shipping.
+//
+// Checkout writes the rates it used onto the receipt, so a return can work
+// out the tax and the points again for what the customer keeps, even if
+// the policy has changed since.
+
+use std::collections::HashMap;
+
+use crate::SupermarketItem;
+use crate::money::round_cents;
+use crate::receipt::Receipt;
+use crate::returns::Refund;
+
+#[derive(Debug, Clone)]
+pub struct StorePolicy {
+    // Percentages.
+    default_tax: f64,
+    tax_by_category: HashMap<String, f64>,
+    pub points_per_unit: u32,
+}
+
+#[derive(Debug)]
+pub struct LoyaltyAccount {
+    pub customer_id: String,
+    pub points: u32,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum PolicyError {
+    // Negative, NaN or infinite.
+    InvalidTaxRate(f64),
+}
+
+fn check_rate(percent: f64) -> Result<f64, PolicyError> {
+    if !percent.is_finite() || percent < 0.0 {
+        return Err(PolicyError::InvalidTaxRate(percent));
+    }
+    Ok(percent)
+}
+
+// Tax at `percent` on `net`.
+pub fn tax_on(net: f64, percent: f64) -> f64 {
+    round_cents(net * percent / 100.0)
+}
+
+pub fn points_for(spent: f64, points_per_unit: u32) -> u32 {
+    spent.max(0.0).floor() as u32 * points_per_unit
+}
+
+impl StorePolicy {
+    pub fn new(default_tax: f64, points_per_unit: u32) -> Result<StorePolicy, PolicyError> {
+        Ok(StorePolicy {
+            default_tax: check_rate(default_tax)?,
+            tax_by_category: HashMap::new(),
+            points_per_unit,
+        })
+    }
+
+    // A different rate for one category, e.g. 0% on groceries.
+    pub fn tax_category(
+        mut self,
+        category: &str,
+        percent: f64,
+    ) -> Result<StorePolicy, PolicyError> {
+        self.tax_by_category
+            .insert(category.to_string(), check_rate(percent)?);
+        Ok(self)
+    }
+
+    pub fn tax_rate(&self, item: &SupermarketItem) -> f64 {
+        item.category
+            .as_ref()
+            .and_then(|category| self.tax_by_category.get(category))
+            .copied()
+            .unwrap_or(self.default_tax)
+    }
+}
+
+impl LoyaltyAccount {
+    pub fn new(customer_id: &str) -> LoyaltyAccount {
+        LoyaltyAccount {
+            customer_id: customer_id.to_string(),
+            points: 0,
+        }
+    }
+
+    pub fn earn(&mut self, receipt: &Receipt) {
+        self.points += receipt.points;
+    }
+
+    // Points already spent can't be taken back below zero.
+    pub fn reverse(&mut self, refund: &Refund) {
+        self.points = self.points.saturating_sub(refund.points);
+    }
+}
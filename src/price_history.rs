@@ -0,0 +1,162 @@
+// Per-product price history.
+// Every price a product has had is kept with the moment it took effect and
+// where it came from, so a repricing closure no longer loses the old price
+// and questions like "what did bananas cost last month" can be answered.
+// Products are keyed by their normalised name, so "Bananas" and "BANANAS"
+// share one history.
+
+use std::collections::HashMap;
+
+use crate::clock::Clock;
+use crate::error::CartError;
+use crate::money::round_cents;
+use crate::normalize::{NormalizeOptions, normalize_name};
+use crate::{ShoppingCart, SupermarketItem};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PriceSource {
+    // The price a product already had when its first change was recorded.
+    Initial,
+    Promotion,
+    ManualOverride,
+    CatalogUpdate,
+}
+
+#[derive(Debug)]
+pub struct PriceChange {
+    pub price: f64,
+    pub effective_from: u64,
+    pub source: PriceSource,
+}
+
+#[derive(Debug)]
+pub struct PriceReport {
+    // Weighted by how long each price was in effect during the window.
+    pub average: f64,
+    pub min: f64,
+    pub max: f64,
+    // From the price at the start of the window to the price at its end.
+    // None when the opening price was 0.00, as there's nothing to compare to.
+    pub change_percent: Option<f64>,
+}
+
+#[derive(Debug, Default)]
+pub struct PriceHistory {
+    products: HashMap<String, Vec<PriceChange>>,
+}
+
+fn key(product: &str) -> String {
+    normalize_name(product, NormalizeOptions::default())
+}
+
+impl PriceHistory {
+    pub fn new() -> PriceHistory {
+        PriceHistory::default()
+    }
+
+    pub fn record(&mut self, product: &str, price: f64, effective_from: u64, source: PriceSource) {
+        let changes = self.products.entry(key(product)).or_default();
+        changes.push(PriceChange {
+            price,
+            effective_from,
+            source,
+        });
+        // Changes can be recorded late (a back-dated catalog update), so
+        // keep them in effective order.
+        changes.sort_by_key(|change| change.effective_from);
+    }
+
+    // A change from `old` to `new`. If the product has no history yet, `old`
+    // is recorded first, as effective from the start of the history (it's
+    // not known when it took effect), so the change can still be reported.
+    pub fn record_change(
+        &mut self,
+        product: &str,
+        old: f64,
+        new: f64,
+        effective_from: u64,
+        source: PriceSource,
+    ) {
+        if self.history(product).is_empty() {
+            self.record(product, old, 0, PriceSource::Initial);
+        }
+        self.record(product, new, effective_from, source);
+    }
+
+    pub fn history(&self, product: &str) -> &[PriceChange] {
+        self.products
+            .get(&key(product))
+            .map_or(&[], |changes| changes.as_slice())
+    }
+
+    pub fn price_at(&self, product: &str, at: u64) -> Option<f64> {
+        self.history(product)
+            .iter()
+            .rev()
+            .find(|change| change.effective_from <= at)
+            .map(|change| change.price)
+    }
+
+    // Report over the window [from, to). None if the product had no price
+    // yet at `from`, or the window is empty.
+    pub fn report(&self, product: &str, from: u64, to: u64) -> Option<PriceReport> {
+        if to <= from {
+            return None;
+        }
+        let opening = self.price_at(product, from)?;
+        let closing = self.price_at(product, to - 1)?;
+
+        // The prices in effect during the window, each with when it started
+        // (clamped to the window) and when the next one took over.
+        let mut spans = vec![(from, opening)];
+        for change in self.history(product) {
+            if change.effective_from > from && change.effective_from < to {
+                spans.push((change.effective_from, change.price));
+            }
+        }
+
+        let mut weighted = 0.0;
+        for (index, (start, price)) in spans.iter().enumerate() {
+            let end = spans.get(index + 1).map_or(to, |(next, _)| *next);
+            weighted += price * (end - start) as f64;
+        }
+        let prices = spans.iter().map(|(_, price)| *price);
+
+        Some(PriceReport {
+            average: round_cents(weighted / (to - from) as f64),
+            min: prices.clone().fold(f64::INFINITY, f64::min),
+            max: prices.fold(f64::NEG_INFINITY, f64::max),
+            change_percent: (opening != 0.0).then(|| (closing - opening) / opening * 100.0),
+        })
+    }
+}
+
+impl ShoppingCart {
+    // traverse_items, but any price the closure changes is recorded in the
+    // history as effective from the clock's current time.
+    pub fn reprice<C, F>(
+        &mut self,
+        history: &mut PriceHistory,
+        source: PriceSource,
+        clock: &C,
+        operation: F,
+    ) -> Result<(), CartError>
+    where
+        C: Clock,
+        F: FnMut(&mut SupermarketItem),
+    {
+        let at = clock.now();
+        let before: Vec<f64> = self.item.iter().map(|item| item.price).collect();
+        // If the closure leaves a line invalid, traverse_items puts the whole
+        // cart back, so comparing afterwards only picks up prices that
+        // really changed.
+        let result = self.traverse_items(operation);
+
+        for (item, old_price) in self.item.iter().zip(before) {
+            if item.price != old_price {
+                history.record_change(&item.name, old_price, item.price, at, source);
+            }
+        }
+        result
+    }
+}
@@ -0,0 +1,667 @@
+// CSV import and export for the catalog and carts, so buyers can keep
+// prices in a spreadsheet. Every spreadsheet names its columns differently
+// ("Item Code", "Unit Price"...), so a ColumnMapping says which header
+// holds which field; unmapped columns are ignored.
+//
+// Import never stops at the first bad row: every row that fails to parse
+// or validate is reported with its line number and the rest still load.
+// Before a bulk repricing, diff_prices shows what would change without
+// touching the catalog; apply_prices then makes the change and records it
+// in the price history. For products the catalog already lists, only the
+// price, allergens and dietary tags are updated, and the last two only
+// when the file has a column for them.
+//
+// Quoted fields may contain commas and doubled quotes (""), but not line
+// breaks. Allergens and dietary tags are lists in one field, separated by
+// semicolons ("milk; soy").
+
+use std::collections::HashSet;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+use crate::catalog::Catalog;
+use crate::clock::Clock;
+use crate::error::CartError;
+use crate::gift_card::STORE_CURRENCY;
+use crate::locale::{currency_in, currency_symbol};
+use crate::nutrition::{Allergen, DietTag};
+use crate::price_history::{PriceHistory, PriceSource};
+use crate::{ShoppingCart, SupermarketItem};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Column {
+    Sku,
+    Name,
+    Price,
+    Quantity,
+    Category,
+    Aisle,
+    Allergens,
+    Diet,
+}
+
+// Which header holds which field, in the order columns are exported.
+#[derive(Debug, Clone)]
+pub struct ColumnMapping {
+    columns: Vec<(Column, String)>,
+}
+
+#[derive(Debug)]
+pub struct PriceRow {
+    pub line: usize,
+    pub sku: Option<String>,
+    pub item: SupermarketItem,
+}
+
+#[derive(Debug)]
+pub struct RowError {
+    pub line: usize,
+    pub message: String,
+}
+
+#[derive(Debug, Default)]
+pub struct PriceList {
+    pub rows: Vec<PriceRow>,
+    pub errors: Vec<RowError>,
+    // The mapped columns the file actually has.
+    columns: Vec<Column>,
+}
+
+#[derive(Debug)]
+pub struct PriceUpdate {
+    pub sku: String,
+    pub name: String,
+    pub old: f64,
+    pub new: f64,
+}
+
+// New allergens and dietary tags for a product the catalog lists.
+#[derive(Debug)]
+pub struct LabelUpdate {
+    pub sku: String,
+    pub name: String,
+    pub allergens: Option<(Vec<Allergen>, Vec<Allergen>)>,
+    pub diet: Option<(Vec<DietTag>, Vec<DietTag>)>,
+}
+
+#[derive(Debug, Default)]
+pub struct PriceDiff {
+    pub changed: Vec<PriceUpdate>,
+    pub relabelled: Vec<LabelUpdate>,
+    // SKUs the catalog doesn't list yet.
+    pub added: Vec<String>,
+    pub unchanged: usize,
+    // Rows that can't be matched to the catalog because they have no SKU.
+    pub missing_sku: Vec<usize>,
+}
+
+impl Default for ColumnMapping {
+    // sku, name, price, quantity, category, aisle, allergens, diet, under
+    // those headers.
+    fn default() -> ColumnMapping {
+        ColumnMapping::new()
+            .column(Column::Sku, "sku")
+            .column(Column::Name, "name")
+            .column(Column::Price, "price")
+            .column(Column::Quantity, "quantity")
+            .column(Column::Category, "category")
+            .column(Column::Aisle, "aisle")
+            .column(Column::Allergens, "allergens")
+            .column(Column::Diet, "diet")
+    }
+}
+
+impl ColumnMapping {
+    // An empty mapping, to be filled in with `column`.
+    pub fn new() -> ColumnMapping {
+        ColumnMapping {
+            columns: Vec::new(),
+        }
+    }
+
+    pub fn column(mut self, column: Column, header: &str) -> ColumnMapping {
+        self.columns.retain(|(existing, _)| *existing != column);
+        self.columns.push((column, header.to_string()));
+        self
+    }
+
+    fn header(&self, column: Column) -> Option<&str> {
+        self.columns
+            .iter()
+            .find(|(existing, _)| *existing == column)
+            .map(|(_, header)| header.as_str())
+    }
+}
+
+// Splits one CSV record into its fields.
+pub(crate) fn parse_record(line: &str) -> Result<Vec<String>, String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut chars = line.chars().peekable();
+    let mut quoted = false;
+
+    while let Some(c) = chars.next() {
+        match (c, quoted) {
+            ('"', true) if chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            ('"', true) => quoted = false,
+            ('"', false) if field.trim().is_empty() => {
+                field.clear();
+                quoted = true;
+            }
+            (',', false) => fields.push(std::mem::take(&mut field)),
+            _ => field.push(c),
+        }
+    }
+    if quoted {
+        return Err("unterminated quoted field".to_string());
+    }
+    fields.push(field);
+    Ok(fields)
+}
+
+pub(crate) fn quote(field: &str) -> String {
+    if field.contains([',', '"', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+fn to_row(mapping: &ColumnMapping, mut field: impl FnMut(Column) -> String) -> String {
+    mapping
+        .columns
+        .iter()
+        .map(|(column, _)| quote(&field(*column)))
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+fn item_field(item: &SupermarketItem, column: Column) -> String {
+    match column {
+        Column::Sku => String::new(),
+        Column::Name => item.name.clone(),
+        Column::Price => format!("{:.2}", item.price),
+        Column::Quantity => item.quantity.to_string(),
+        Column::Category => item.category.clone().unwrap_or_default(),
+        Column::Aisle => item
+            .aisle
+            .map(|aisle| aisle.to_string())
+            .unwrap_or_default(),
+        Column::Allergens => join_list(&item.allergens),
+        Column::Diet => join_list(&item.diet),
+    }
+}
+
+fn join_list<T: fmt::Display>(values: &[T]) -> String {
+    values
+        .iter()
+        .map(|value| value.to_string())
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+// The entries of a semicolon-separated list field.
+fn parse_list<T>(text: &str) -> Result<Vec<T>, String>
+where
+    T: FromStr<Err = String>,
+{
+    text.split(';')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .map(str::parse)
+        .collect()
+}
+
+impl PriceList {
+    pub fn load(path: impl AsRef<Path>, mapping: &ColumnMapping) -> std::io::Result<PriceList> {
+        Ok(PriceList::parse(&fs::read_to_string(path)?, mapping))
+    }
+
+    // The first non-blank line is the header. Name and price columns are
+    // required; the rest are optional.
+    pub fn parse(text: &str, mapping: &ColumnMapping) -> PriceList {
+        let mut list = PriceList::default();
+        let mut records = text
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty());
+
+        let Some((header_index, header)) = records.next() else {
+            return list;
+        };
+        let header_line = header_index + 1;
+        let headers = match parse_record(header) {
+            Ok(headers) => headers,
+            Err(message) => {
+                list.errors.push(RowError {
+                    line: header_line,
+                    message,
+                });
+                return list;
+            }
+        };
+        // Where each mapped column is in this file, if it's there at all.
+        let positions: Vec<(Column, usize)> = mapping
+            .columns
+            .iter()
+            .filter_map(|(column, wanted)| {
+                let at = headers
+                    .iter()
+                    .position(|header| header.trim().eq_ignore_ascii_case(wanted.trim()))?;
+                Some((*column, at))
+            })
+            .collect();
+        list.columns = positions.iter().map(|(column, _)| *column).collect();
+        let found = |column| positions.iter().any(|(existing, _)| *existing == column);
+        if !found(Column::Name) || !found(Column::Price) {
+            list.errors.push(RowError {
+                line: header_line,
+                message: "the header needs a name and a price column".to_string(),
+            });
+            return list;
+        }
+
+        let mut seen = HashSet::new();
+        for (index, record) in records {
+            let line = index + 1;
+            match parse_row(line, record, &positions) {
+                Ok(row) => {
+                    if let Some(sku) = &row.sku
+                        && !seen.insert(sku.clone())
+                    {
+                        list.errors.push(RowError {
+                            line,
+                            message: format!("SKU {} is listed twice", sku),
+                        });
+                        continue;
+                    }
+                    list.rows.push(row);
+                }
+                Err(message) => list.errors.push(RowError { line, message }),
+            }
+        }
+
+        list
+    }
+
+    // The rows as cart lines.
+    pub fn to_cart(&self) -> Result<ShoppingCart, CartError> {
+        ShoppingCart::new(self.rows.iter().map(|row| row.item.clone()).collect())
+    }
+}
+
+fn parse_row(line: usize, record: &str, positions: &[(Column, usize)]) -> Result<PriceRow, String> {
+    let fields = parse_record(record)?;
+    // Missing trailing fields and empty ones both count as not given.
+    let field = |column: Column| {
+        positions
+            .iter()
+            .find(|(existing, _)| *existing == column)
+            .and_then(|(_, at)| fields.get(*at))
+            .map(|field| field.trim())
+            .filter(|field| !field.is_empty())
+    };
+
+    let name = field(Column::Name).unwrap_or_default();
+    let price_text = field(Column::Price).ok_or("price is missing")?;
+    // Spreadsheets often keep the currency symbol. Only the store's own is
+    // dropped: a price in another currency can't be read as one in ours.
+    let symbol = currency_symbol(STORE_CURRENCY).unwrap_or(STORE_CURRENCY);
+    let amount = price_text.strip_prefix(symbol).unwrap_or(price_text);
+    if let Some(currency) = currency_in(amount).filter(|code| *code != STORE_CURRENCY) {
+        return Err(format!(
+            "price `{}` is in {}, not {}",
+            price_text, currency, STORE_CURRENCY
+        ));
+    }
+    let price = amount
+        .parse()
+        .map_err(|_| format!("price `{}` is not a number", price_text))?;
+
+    let mut builder = SupermarketItem::builder(name, price);
+    if let Some(text) = field(Column::Quantity) {
+        let quantity = text
+            .parse()
+            .map_err(|_| format!("quantity `{}` is not a whole number", text))?;
+        builder = builder.qty(quantity);
+    }
+    if let Some(category) = field(Column::Category) {
+        builder = builder.category(category);
+    }
+    if let Some(text) = field(Column::Aisle) {
+        let aisle = text
+            .parse()
+            .map_err(|_| format!("aisle `{}` is not a whole number", text))?;
+        builder = builder.aisle(aisle);
+    }
+    if let Some(text) = field(Column::Allergens) {
+        for allergen in parse_list::<Allergen>(text)? {
+            builder = builder.allergen(allergen);
+        }
+    }
+    if let Some(text) = field(Column::Diet) {
+        for tag in parse_list::<DietTag>(text)? {
+            builder = builder.diet(tag);
+        }
+    }
+    let item = builder.build().map_err(|error| error.to_string())?;
+
+    Ok(PriceRow {
+        line,
+        sku: field(Column::Sku).map(str::to_string),
+        item,
+    })
+}
+
+impl Catalog {
+    // What importing `list` would do to the catalog's prices, without
+    // changing anything.
+    pub fn diff_prices(&self, list: &PriceList) -> PriceDiff {
+        let mut diff = PriceDiff::default();
+        let has = |column| list.columns.contains(&column);
+        for row in &list.rows {
+            let Some(sku) = &row.sku else {
+                diff.missing_sku.push(row.line);
+                continue;
+            };
+            let Some(entry) = self.get(sku) else {
+                diff.added.push(sku.clone());
+                continue;
+            };
+            let (old, new) = (&entry.item, &row.item);
+
+            let allergens = (has(Column::Allergens) && old.allergens != new.allergens)
+                .then(|| (old.allergens.clone(), new.allergens.clone()));
+            let diet = (has(Column::Diet) && old.diet != new.diet)
+                .then(|| (old.diet.clone(), new.diet.clone()));
+            let relabelled = allergens.is_some() || diet.is_some();
+            if relabelled {
+                diff.relabelled.push(LabelUpdate {
+                    sku: sku.clone(),
+                    name: old.name.clone(),
+                    allergens,
+                    diet,
+                });
+            }
+            if old.price != new.price {
+                diff.changed.push(PriceUpdate {
+                    sku: sku.clone(),
+                    name: old.name.clone(),
+                    old: old.price,
+                    new: new.price,
+                });
+            } else if !relabelled {
+                diff.unchanged += 1;
+            }
+        }
+        diff
+    }
+
+    // Applies the price, allergen and dietary tag changes in `list` and
+    // adds the products the catalog doesn't list yet, recording every new
+    // price in `history` as effective from the clock's current time.
+    // Returns what changed.
+    pub fn apply_prices<C>(
+        &mut self,
+        list: &PriceList,
+        history: &mut PriceHistory,
+        clock: &C,
+    ) -> PriceDiff
+    where
+        C: Clock,
+    {
+        let at = clock.now();
+        let diff = self.diff_prices(list);
+        for update in &diff.changed {
+            if let Some(entry) = self.get(&update.sku) {
+                let mut item = entry.item.clone();
+                item.price = update.new;
+                if self.add(&update.sku, item).is_ok() {
+                    history.record_change(
+                        &update.name,
+                        update.old,
+                        update.new,
+                        at,
+                        PriceSource::CatalogUpdate,
+                    );
+                }
+            }
+        }
+        for update in &diff.relabelled {
+            if let Some(entry) = self.get(&update.sku) {
+                let mut item = entry.item.clone();
+                if let Some((_, allergens)) = &update.allergens {
+                    item.allergens = allergens.clone();
+                }
+                if let Some((_, diet)) = &update.diet {
+                    item.diet = diet.clone();
+                }
+                // Re-adding an item that was already valid can't fail.
+                let _ = self.add(&update.sku, item);
+            }
+        }
+        for row in &list.rows {
+            if let Some(sku) = &row.sku
+                && diff.added.contains(sku)
+                && self.add(sku, row.item.clone()).is_ok()
+            {
+                history.record(
+                    &row.item.name,
+                    row.item.price,
+                    at,
+                    PriceSource::CatalogUpdate,
+                );
+            }
+        }
+        diff
+    }
+
+    pub fn to_csv(&self, mapping: &ColumnMapping) -> String {
+        let mut csv = to_row(mapping, |column| {
+            mapping.header(column).unwrap_or_default().to_string()
+        });
+        for entry in self.entries() {
+            csv.push('\n');
+            csv.push_str(&to_row(mapping, |column| match column {
+                Column::Sku => entry.sku.clone(),
+                other => item_field(&entry.item, other),
+            }));
+        }
+        csv
+    }
+}
+
+impl ShoppingCart {
+    // Cart lines have no SKU, so a SKU column is left empty.
+    pub fn to_csv(&self, mapping: &ColumnMapping) -> String {
+        let mut csv = to_row(mapping, |column| {
+            mapping.header(column).unwrap_or_default().to_string()
+        });
+        for item in &self.item {
+            csv.push('\n');
+            csv.push_str(&to_row(mapping, |column| item_field(item, column)));
+        }
+        csv
+    }
+}
+
+impl fmt::Display for RowError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl fmt::Display for PriceDiff {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for update in &self.changed {
+            write!(
+                f,
+                "{} {}: {:.2} -> {:.2}",
+                update.sku, update.name, update.old, update.new
+            )?;
+            if update.old > 0.0 {
+                write!(
+                    f,
+                    " ({:+.1}%)",
+                    (update.new - update.old) / update.old * 100.0
+                )?;
+            }
+            writeln!(f)?;
+        }
+        for update in &self.relabelled {
+            if let Some((old, new)) = &update.allergens {
+                writeln!(
+                    f,
+                    "{} {}: allergens [{}] -> [{}]",
+                    update.sku,
+                    update.name,
+                    join_list(old),
+                    join_list(new)
+                )?;
+            }
+            if let Some((old, new)) = &update.diet {
+                writeln!(
+                    f,
+                    "{} {}: diet [{}] -> [{}]",
+                    update.sku,
+                    update.name,
+                    join_list(old),
+                    join_list(new)
+                )?;
+            }
+        }
+        for sku in &self.added {
+            writeln!(f, "{}: new product", sku)?;
+        }
+        for line in &self.missing_sku {
+            writeln!(f, "line {}: no SKU, skipped", line)?;
+        }
+        write!(
+            f,
+            "{} changed, {} relabelled, {} new, {} unchanged",
+            self.changed.len(),
+            self.relabelled.len(),
+            self.added.len(),
+            self.unchanged
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn allergens_and_diet_tags_are_imported() {
+        let csv = "name,price,allergens,diet\n\
+                   CHOCOLATE,1.59,Milk; soya,vegetarian\n\
+                   OAT DRINK,2.10,,Vegan; gluten free\n";
+        let list = PriceList::parse(csv, &ColumnMapping::default());
+
+        assert!(list.errors.is_empty());
+        assert_eq!(list.rows[0].item.allergens, [Allergen::Milk, Allergen::Soy]);
+        assert_eq!(list.rows[0].item.diet, [DietTag::Vegetarian]);
+        assert!(list.rows[1].item.allergens.is_empty());
+        assert_eq!(
+            list.rows[1].item.diet,
+            [DietTag::Vegan, DietTag::GlutenFree]
+        );
+    }
+
+    #[test]
+    fn an_unknown_allergen_rejects_the_row() {
+        let csv = "name,price,allergens\nTRAIL MIX,3.40,peanuts; raisins\n";
+        let list = PriceList::parse(csv, &ColumnMapping::default());
+
+        assert!(list.rows.is_empty());
+        assert_eq!(list.errors[0].line, 2);
+        assert_eq!(list.errors[0].message, "unknown allergen `raisins`");
+    }
+
+    #[test]
+    fn only_the_store_currency_symbol_is_dropped() {
+        let csv = "name,price\n\
+                   CHOCOLATE,$1.59\n\
+                   OAT DRINK,€2.10\n\
+                   SODA,0.99 GBP\n\
+                   MILK,¥300\n\
+                   BREAD,$$2.49\n";
+        let list = PriceList::parse(csv, &ColumnMapping::default());
+
+        assert_eq!(list.rows.len(), 1);
+        assert_eq!(list.rows[0].item.price, 1.59);
+        let messages: Vec<&str> = list
+            .errors
+            .iter()
+            .map(|error| error.message.as_str())
+            .collect();
+        assert_eq!(
+            messages,
+            [
+                "price `€2.10` is in EUR, not USD",
+                "price `0.99 GBP` is in GBP, not USD",
+                "price `¥300` is in JPY, not USD",
+                "price `$$2.49` is not a number",
+            ]
+        );
+    }
+
+    #[test]
+    fn exported_allergens_and_diet_tags_import_again() {
+        let item = SupermarketItem::builder("PEANUT BAR", 1.20)
+            .allergen(Allergen::TreeNuts)
+            .allergen(Allergen::Peanuts)
+            .diet(DietTag::GlutenFree)
+            .build()
+            .unwrap();
+        let cart = ShoppingCart::new(vec![item]).unwrap();
+
+        let csv = cart.to_csv(&ColumnMapping::default());
+        let list = PriceList::parse(&csv, &ColumnMapping::default());
+
+        assert!(list.errors.is_empty());
+        assert_eq!(
+            list.rows[0].item.allergens,
+            [Allergen::TreeNuts, Allergen::Peanuts]
+        );
+        assert_eq!(list.rows[0].item.diet, [DietTag::GlutenFree]);
+    }
+
+    fn catalog() -> Catalog {
+        let mut catalog = Catalog::new();
+        let chocolate = SupermarketItem::builder("CHOCOLATE", 1.49)
+            .diet(DietTag::Vegetarian)
+            .build()
+            .unwrap();
+        catalog.add("1003", chocolate).unwrap();
+        catalog
+    }
+
+    #[test]
+    fn allergens_and_diet_tags_are_applied_to_known_products() {
+        let mut catalog = catalog();
+        let csv = "sku,name,price,allergens\n1003,CHOCOLATE,1.49,milk; soy\n";
+        let list = PriceList::parse(csv, &ColumnMapping::default());
+
+        let diff = catalog.diff_prices(&list);
+        assert_eq!((diff.changed.len(), diff.unchanged), (0, 0));
+        assert_eq!(
+            diff.relabelled[0].allergens,
+            Some((Vec::new(), vec![Allergen::Milk, Allergen::Soy]))
+        );
+        // The file has no diet column, so the diet is left alone.
+        assert_eq!(diff.relabelled[0].diet, None);
+
+        let clock = || 1_000;
+        catalog.apply_prices(&list, &mut PriceHistory::new(), &clock);
+        let item = &catalog.get("1003").unwrap().item;
+        assert_eq!(item.allergens, [Allergen::Milk, Allergen::Soy]);
+        assert_eq!(item.diet, [DietTag::Vegetarian]);
+        assert_eq!(catalog.diff_prices(&list).unchanged, 1);
+    }
+}
@@ -0,0 +1,569 @@
+// Promotions: happy hours, weekend deals, end-of-day markdowns and
+// multi-buys ("3 for 2"). Each promotion has a schedule and a closure that
+// picks the lines it applies to, and is only active while the store's local
+// time is inside its schedule, as read from the Clock it's given.
+//
+// A price cut lowers the unit price. When several active price cuts cover
+// the same line, only the biggest is given; they never stack. A multi-buy
+// doesn't touch the unit price: every full group of `buy` units on the line
+// only pays for `pay` of them, so it can stop qualifying when some of the
+// units come back (see returns.rs).
+//
+// Each line remembers its list price and the promotions on it, so applying
+// promotions again (later in the day, say) starts from the list price
+// rather than discounting twice. Price changes go through `reprice`, so the
+// price history records them.
+
+use std::fmt;
+
+use crate::clock::{Clock, LocalTime};
+use crate::error::CartError;
+use crate::money::round_cents;
+use crate::price_history::{PriceHistory, PriceSource};
+use crate::{ShoppingCart, SupermarketItem};
+
+// Times of day are minutes after local midnight.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Schedule {
+    Always,
+    // Every day from `from` until just before `to`. A window that ends
+    // before it starts runs past midnight, e.g. 22:00 until 02:00.
+    HappyHour { from: u32, to: u32 },
+    // All day Saturday and Sunday.
+    Weekend,
+    // Every day from `from` until closing.
+    EndOfDay { from: u32 },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Discount {
+    PercentOff(f64),
+    MultiBuy { buy: u32, pay: u32 },
+}
+
+pub struct Promotion {
+    pub name: String,
+    pub schedule: Schedule,
+    pub discount: Discount,
+    applies_to: Box<dyn Fn(&SupermarketItem) -> bool>,
+}
+
+pub struct Promotions {
+    // The store's offset from UTC, in minutes.
+    utc_offset: i32,
+    promotions: Vec<Promotion>,
+}
+
+// A price cut on a cart line, and the line's price before it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PriceCut {
+    pub name: String,
+    pub list_price: f64,
+}
+
+// A multi-buy a cart line was sold under.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MultiBuy {
+    pub name: String,
+    pub buy: u32,
+    pub pay: u32,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum PromotionError {
+    // A price cut must be between 0% and 100% off.
+    InvalidPercent(f64),
+    // A multi-buy must pay for at least one unit and fewer than it buys.
+    InvalidMultiBuy { buy: u32, pay: u32 },
+    // A time past midnight (1440 minutes), or an empty happy hour.
+    BadSchedule(Schedule),
+}
+
+#[derive(Debug)]
+pub struct AppliedPromotion {
+    pub line: usize,
+    pub promotion: String,
+    // The line total at list price, and with the promotions.
+    pub before: f64,
+    pub after: f64,
+}
+
+impl Schedule {
+    pub fn is_active(&self, time: LocalTime) -> bool {
+        match *self {
+            Schedule::Always => true,
+            Schedule::HappyHour { from, to } if from <= to => {
+                (from..to).contains(&time.minute_of_day)
+            }
+            Schedule::HappyHour { from, to } => {
+                time.minute_of_day >= from || time.minute_of_day < to
+            }
+            Schedule::Weekend => time.weekday.is_weekend(),
+            Schedule::EndOfDay { from } => time.minute_of_day >= from,
+        }
+    }
+
+    // Starts before midnight, and a happy hour ends by the next one and
+    // isn't empty.
+    pub fn is_valid(&self) -> bool {
+        const DAY: u32 = 24 * 60;
+        match *self {
+            Schedule::Always | Schedule::Weekend => true,
+            Schedule::HappyHour { from, to } => from < DAY && to <= DAY && from != to,
+            Schedule::EndOfDay { from } => from < DAY,
+        }
+    }
+}
+
+impl MultiBuy {
+    // Pays for at least one unit and fewer than it buys.
+    pub fn is_valid(buy: u32, pay: u32) -> bool {
+        pay >= 1 && pay < buy
+    }
+
+    // What `quantity` units at `price` save under this deal.
+    pub fn discount(&self, quantity: u32, price: f64) -> f64 {
+        let free = quantity / self.buy * (self.buy - self.pay);
+        round_cents(free as f64 * price)
+    }
+}
+
+// What `quantity` units at `price` cost, after any multi-buy.
+pub fn line_total(price: f64, quantity: u32, multi_buy: Option<&MultiBuy>) -> f64 {
+    let full = round_cents(price * quantity as f64);
+    round_cents(full - multi_buy.map_or(0.0, |deal| deal.discount(quantity, price)))
+}
+
+impl SupermarketItem {
+    pub fn list_price(&self) -> f64 {
+        self.price_cut
+            .as_ref()
+            .map_or(self.price, |cut| cut.list_price)
+    }
+
+    pub fn line_total(&self) -> f64 {
+        line_total(self.price, self.quantity, self.multi_buy.as_ref())
+    }
+}
+
+impl Promotions {
+    pub fn new(utc_offset: i32) -> Promotions {
+        Promotions {
+            utc_offset,
+            promotions: Vec::new(),
+        }
+    }
+
+    pub fn add<F>(
+        &mut self,
+        name: &str,
+        schedule: Schedule,
+        percent_off: f64,
+        applies_to: F,
+    ) -> Result<(), PromotionError>
+    where
+        F: Fn(&SupermarketItem) -> bool + 'static,
+    {
+        if !schedule.is_valid() {
+            return Err(PromotionError::BadSchedule(schedule));
+        }
+        if !(0.0..=100.0).contains(&percent_off) {
+            return Err(PromotionError::InvalidPercent(percent_off));
+        }
+        self.promotions.push(Promotion {
+            name: name.to_string(),
+            schedule,
+            discount: Discount::PercentOff(percent_off),
+            applies_to: Box::new(applies_to),
+        });
+        Ok(())
+    }
+
+    // "Buy 3, pay for 2" is `buy: 3, pay: 2`.
+    pub fn add_multi_buy<F>(
+        &mut self,
+        name: &str,
+        schedule: Schedule,
+        buy: u32,
+        pay: u32,
+        applies_to: F,
+    ) -> Result<(), PromotionError>
+    where
+        F: Fn(&SupermarketItem) -> bool + 'static,
+    {
+        if !schedule.is_valid() {
+            return Err(PromotionError::BadSchedule(schedule));
+        }
+        if !MultiBuy::is_valid(buy, pay) {
+            return Err(PromotionError::InvalidMultiBuy { buy, pay });
+        }
+        self.promotions.push(Promotion {
+            name: name.to_string(),
+            schedule,
+            discount: Discount::MultiBuy { buy, pay },
+            applies_to: Box::new(applies_to),
+        });
+        Ok(())
+    }
+
+    pub fn active<C>(&self, clock: &C) -> Vec<&Promotion>
+    where
+        C: Clock,
+    {
+        let time = LocalTime::at(clock.now(), self.utc_offset);
+        self.promotions
+            .iter()
+            .filter(|promotion| promotion.schedule.is_active(time))
+            .collect()
+    }
+
+    // The biggest active price cut (its percentage and name) and the best
+    // active multi-buy for `item`.
+    fn best_for(
+        active: &[&Promotion],
+        item: &SupermarketItem,
+    ) -> (Option<(f64, String)>, Option<MultiBuy>) {
+        let mut cut: Option<(f64, &Promotion)> = None;
+        let mut multi_buy: Option<(f64, MultiBuy)> = None;
+
+        for promotion in active
+            .iter()
+            .filter(|promotion| (promotion.applies_to)(item))
+        {
+            match promotion.discount {
+                Discount::PercentOff(percent) => {
+                    if cut.is_none_or(|(best, _)| percent > best) {
+                        cut = Some((percent, promotion));
+                    }
+                }
+                Discount::MultiBuy { buy, pay } => {
+                    // Compared on what it's worth off one full group.
+                    let saving = (buy - pay) as f64 / buy as f64;
+                    if multi_buy.as_ref().is_none_or(|(best, _)| saving > *best) {
+                        let deal = MultiBuy {
+                            name: promotion.name.clone(),
+                            buy,
+                            pay,
+                        };
+                        multi_buy = Some((saving, deal));
+                    }
+                }
+            }
+        }
+
+        (
+            cut.map(|(percent, promotion)| (percent, promotion.name.clone())),
+            multi_buy.map(|(_, deal)| deal),
+        )
+    }
+}
+
+impl ShoppingCart {
+    // Puts every line on the promotions active at `clock`'s time, starting
+    // from its list price, and takes it off any that have ended.
+    pub fn apply_promotions<C>(
+        &mut self,
+        promotions: &Promotions,
+        history: &mut PriceHistory,
+        clock: &C,
+    ) -> Result<Vec<AppliedPromotion>, CartError>
+    where
+        C: Clock,
+    {
+        let active = promotions.active(clock);
+        let mut applied = Vec::new();
+
+        // Work out every line first, then reprice them all in one go. `add`
+        // only takes cuts between 0% and 100%, so every planned price is
+        // valid, and a line reprice did reject would put the whole cart
+        // back anyway: the cart and the history are never half-promoted.
+        let mut planned = Vec::new();
+        for (line, item) in self.item.iter().enumerate() {
+            let (cut, multi_buy) = Promotions::best_for(&active, item);
+            let list_price = item.list_price();
+            let price = cut.as_ref().map_or(list_price, |(percent, _)| {
+                round_cents(list_price * (1.0 - percent / 100.0))
+            });
+            let cut = cut.map(|(_, name)| PriceCut { name, list_price });
+
+            let names: Vec<&str> = cut
+                .iter()
+                .map(|cut| cut.name.as_str())
+                .chain(multi_buy.iter().map(|deal| deal.name.as_str()))
+                .collect();
+            if !names.is_empty() {
+                applied.push(AppliedPromotion {
+                    line,
+                    promotion: names.join(" + "),
+                    before: line_total(list_price, item.quantity, None),
+                    after: line_total(price, item.quantity, multi_buy.as_ref()),
+                });
+            }
+            planned.push((price, cut, multi_buy));
+        }
+
+        let mut planned = planned.into_iter();
+        self.reprice(history, PriceSource::Promotion, clock, |item| {
+            if let Some((price, cut, multi_buy)) = planned.next() {
+                item.price = price;
+                item.price_cut = cut;
+                item.multi_buy = multi_buy;
+            }
+        })?;
+        Ok(applied)
+    }
+}
+
+impl fmt::Display for AppliedPromotion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "line {}: {} {:.2} -> {:.2}",
+            self.line, self.promotion, self.before, self.after
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::{FakeClock, Weekday};
+    use crate::error::ItemError;
+
+    // Friday 5 January 2024, 17:30 UTC.
+    const FRIDAY_EVENING: u64 = 1_704_475_800;
+    const HOUR: u64 = 3_600;
+
+    fn at(weekday: Weekday, hour: u32, minute: u32) -> LocalTime {
+        LocalTime {
+            weekday,
+            minute_of_day: hour * 60 + minute,
+        }
+    }
+
+    fn promotions() -> Promotions {
+        let mut promotions = Promotions::new(0);
+        promotions
+            .add(
+                "happy hour",
+                Schedule::HappyHour {
+                    from: 17 * 60,
+                    to: 19 * 60,
+                },
+                20.0,
+                |item| item.category.as_deref() == Some("dairy"),
+            )
+            .unwrap();
+        promotions
+            .add("weekend deal", Schedule::Weekend, 10.0, |_| true)
+            .unwrap();
+        promotions
+            .add(
+                "end of day",
+                Schedule::EndOfDay { from: 20 * 60 },
+                50.0,
+                |item| item.name.starts_with("LOOSE"),
+            )
+            .unwrap();
+        promotions
+    }
+
+    fn cart() -> ShoppingCart {
+        ShoppingCart::new(vec![
+            SupermarketItem::builder("MILK", 2.00)
+                .category("dairy")
+                .qty(3)
+                .build()
+                .unwrap(),
+            SupermarketItem::new("LOOSE APPLES", 3.00).unwrap(),
+        ])
+        .unwrap()
+    }
+
+    #[test]
+    fn schedules_cover_their_times() {
+        let happy_hour = Schedule::HappyHour {
+            from: 17 * 60,
+            to: 19 * 60,
+        };
+        assert!(!happy_hour.is_active(at(Weekday::Friday, 16, 59)));
+        assert!(happy_hour.is_active(at(Weekday::Friday, 17, 0)));
+        assert!(happy_hour.is_active(at(Weekday::Friday, 18, 59)));
+        assert!(!happy_hour.is_active(at(Weekday::Friday, 19, 0)));
+
+        let overnight = Schedule::HappyHour {
+            from: 22 * 60,
+            to: 2 * 60,
+        };
+        assert!(!overnight.is_active(at(Weekday::Friday, 21, 59)));
+        assert!(overnight.is_active(at(Weekday::Friday, 22, 0)));
+        assert!(overnight.is_active(at(Weekday::Friday, 23, 59)));
+        assert!(overnight.is_active(at(Weekday::Saturday, 0, 0)));
+        assert!(overnight.is_active(at(Weekday::Saturday, 1, 59)));
+        assert!(!overnight.is_active(at(Weekday::Saturday, 2, 0)));
+        assert!(!overnight.is_active(at(Weekday::Saturday, 12, 0)));
+
+        let until_midnight = Schedule::HappyHour {
+            from: 23 * 60,
+            to: 24 * 60,
+        };
+        assert!(until_midnight.is_active(at(Weekday::Friday, 23, 59)));
+        assert!(!until_midnight.is_active(at(Weekday::Saturday, 0, 0)));
+
+        assert!(Schedule::Weekend.is_active(at(Weekday::Saturday, 9, 0)));
+        assert!(Schedule::Weekend.is_active(at(Weekday::Sunday, 23, 59)));
+        assert!(!Schedule::Weekend.is_active(at(Weekday::Friday, 23, 59)));
+
+        let end_of_day = Schedule::EndOfDay { from: 20 * 60 };
+        assert!(!end_of_day.is_active(at(Weekday::Monday, 19, 59)));
+        assert!(end_of_day.is_active(at(Weekday::Monday, 23, 59)));
+
+        assert!(Schedule::Always.is_active(at(Weekday::Tuesday, 3, 0)));
+    }
+
+    #[test]
+    fn active_promotions_follow_the_clock() {
+        let promotions = promotions();
+        let clock = FakeClock::new(FRIDAY_EVENING);
+        let names = |clock: &FakeClock| -> Vec<String> {
+            promotions
+                .active(clock)
+                .iter()
+                .map(|promotion| promotion.name.clone())
+                .collect()
+        };
+
+        assert_eq!(names(&clock), ["happy hour"]);
+        clock.advance(3 * HOUR);
+        assert_eq!(names(&clock), ["end of day"]);
+        clock.advance(13 * HOUR);
+        assert_eq!(names(&clock), ["weekend deal"]);
+    }
+
+    #[test]
+    fn promotions_start_from_the_list_price_each_time() {
+        let promotions = promotions();
+        let clock = FakeClock::new(FRIDAY_EVENING);
+        let mut history = PriceHistory::new();
+        let mut cart = cart();
+
+        let applied = cart
+            .apply_promotions(&promotions, &mut history, &clock)
+            .unwrap();
+        assert_eq!(applied.len(), 1);
+        assert_eq!(applied[0].promotion, "happy hour");
+        assert_eq!((applied[0].before, applied[0].after), (6.00, 4.80));
+        assert_eq!(cart.item[0].price, 1.60);
+        assert_eq!(cart.item[0].list_price(), 2.00);
+
+        // 20:30: the happy hour is over and the apples are marked down.
+        clock.advance(3 * HOUR);
+        cart.apply_promotions(&promotions, &mut history, &clock)
+            .unwrap();
+        assert_eq!(cart.item[0].price, 2.00);
+        assert_eq!(cart.item[0].price_cut, None);
+        assert_eq!(cart.item[1].price, 1.50);
+
+        let milk: Vec<f64> = history
+            .history("MILK")
+            .iter()
+            .map(|change| change.price)
+            .collect();
+        assert_eq!(milk, [2.00, 1.60, 2.00]);
+    }
+
+    #[test]
+    fn only_the_biggest_cut_applies_and_a_multi_buy_goes_on_top() {
+        let mut promotions = promotions();
+        promotions
+            .add_multi_buy("MILK 3 FOR 2", Schedule::Always, 3, 2, |item| {
+                item.name == "MILK"
+            })
+            .unwrap();
+        // Saturday 6 January 2024, 20:30 UTC: weekend and end of day both
+        // cover the apples.
+        let clock = FakeClock::new(FRIDAY_EVENING + 27 * HOUR);
+        let mut cart = cart();
+
+        let applied = cart
+            .apply_promotions(&promotions, &mut PriceHistory::new(), &clock)
+            .unwrap();
+        assert_eq!(applied[0].promotion, "weekend deal + MILK 3 FOR 2");
+        assert_eq!(cart.item[0].price, 1.80);
+        assert_eq!(cart.item[0].line_total(), 3.60);
+        assert_eq!(applied[1].promotion, "end of day");
+        assert_eq!(cart.item[1].price, 1.50);
+    }
+
+    #[test]
+    fn a_cart_line_cant_be_left_on_a_broken_deal() {
+        let mut cart = cart();
+        let broken = MultiBuy {
+            name: "BROKEN".to_string(),
+            buy: 0,
+            pay: 0,
+        };
+        let result = cart.traverse_items(|item| item.multi_buy = Some(broken.clone()));
+        assert!(matches!(
+            result,
+            Err(CartError::Item {
+                line: 0,
+                error: ItemError::InvalidMultiBuy { buy: 0, pay: 0 }
+            })
+        ));
+        assert_eq!(cart.item[0].multi_buy, None);
+
+        let result = cart.traverse_items(|item| {
+            item.price_cut = Some(PriceCut {
+                name: "TYPO".to_string(),
+                list_price: f64::NAN,
+            })
+        });
+        assert!(result.is_err());
+        assert_eq!(cart.total(), 9.00);
+    }
+
+    #[test]
+    fn invalid_promotions_are_refused() {
+        let mut promotions = Promotions::new(0);
+        for percent in [150.0, -5.0, f64::NAN] {
+            assert!(matches!(
+                promotions.add("typo", Schedule::Always, percent, |_| true),
+                Err(PromotionError::InvalidPercent(_))
+            ));
+        }
+        assert_eq!(
+            promotions.add_multi_buy("bad", Schedule::Always, 2, 2, |_| true),
+            Err(PromotionError::InvalidMultiBuy { buy: 2, pay: 2 })
+        );
+        for schedule in [
+            Schedule::HappyHour {
+                from: 17 * 60,
+                to: 25 * 60,
+            },
+            Schedule::HappyHour {
+                from: 24 * 60,
+                to: 60,
+            },
+            Schedule::HappyHour {
+                from: 17 * 60,
+                to: 17 * 60,
+            },
+            Schedule::EndOfDay { from: 24 * 60 },
+        ] {
+            assert_eq!(
+                promotions.add("late", schedule, 10.0, |_| true),
+                Err(PromotionError::BadSchedule(schedule))
+            );
+            assert_eq!(
+                promotions.add_multi_buy("late", schedule, 3, 2, |_| true),
+                Err(PromotionError::BadSchedule(schedule))
+            );
+        }
+        assert!(
+            promotions
+                .active(&FakeClock::new(FRIDAY_EVENING))
+                .is_empty()
+        );
+    }
+}
@@ -0,0 +1,120 @@
+// A Receipt is what's left once a cart has been paid for.
+// The cart itself is consumed by checkout, so the receipt keeps its own
+// copy of every line and every payment that was taken, along with the
+// promotions, tax rates and points rate each line was sold under, so a
+// return can work all of them out again.
+
+use std::fmt;
+
+use crate::gift_card::STORE_CURRENCY;
+use crate::locale::Locale;
+use crate::money::round_cents;
+use crate::policy::tax_on;
+use crate::promotions::{MultiBuy, line_total};
+use crate::shipping::ShippingOption;
+
+#[derive(Debug)]
+pub struct ReceiptLine {
+    pub name: String,
+    // The unit price charged, after any price cut.
+    pub price: f64,
+    pub list_price: f64,
+    // The price cut's name, if the line was on one.
+    pub price_cut: Option<String>,
+    pub multi_buy: Option<MultiBuy>,
+    // Percent.
+    pub tax_rate: f64,
+    pub quantity: u32,
+    // How many of `quantity` have already been brought back.
+    pub returned: u32,
+}
+
+#[derive(Debug)]
+pub struct Payment {
+    pub method: String,
+    pub amount: f64,
+}
+
+#[derive(Debug)]
+pub struct Receipt {
+    pub lines: Vec<ReceiptLine>,
+    pub shipping: Option<ShippingOption>,
+    pub tax: f64,
+    // Goods, tax and shipping.
+    pub total: f64,
+    pub payments: Vec<Payment>,
+    // Whatever the tenders didn't cover, still to be paid at the till.
+    pub amount_due: f64,
+    pub issued_at: u64,
+    // Points earned on the lines that haven't come back.
+    pub points: u32,
+    pub points_per_unit: u32,
+}
+
+impl ReceiptLine {
+    // What `quantity` units of the line cost, after any multi-buy.
+    pub fn net(&self, quantity: u32) -> f64 {
+        line_total(self.price, quantity, self.multi_buy.as_ref())
+    }
+
+    pub fn tax(&self, quantity: u32) -> f64 {
+        tax_on(self.net(quantity), self.tax_rate)
+    }
+
+    pub fn kept(&self) -> u32 {
+        self.quantity - self.returned
+    }
+}
+
+impl Receipt {
+    // What the goods still kept cost, after promotions and before tax.
+    pub fn goods(&self) -> f64 {
+        round_cents(self.lines.iter().map(|line| line.net(line.kept())).sum())
+    }
+
+    pub fn render(&self, locale: &Locale) -> String {
+        let money = |amount: f64| locale.format_money(amount, STORE_CURRENCY);
+        let mut lines = vec![format!("RECEIPT {}", locale.format_date(self.issued_at))];
+
+        for line in &self.lines {
+            let label = format!("{} x{}", line.name, line.quantity);
+            let full = line.price * line.quantity as f64;
+            lines.push(format!("  {:<26} {:>12}", label, money(full)));
+            if let Some(cut) = &line.price_cut {
+                lines.push(format!("    {} (was {})", cut, money(line.list_price)));
+            }
+            if let Some(deal) = &line.multi_buy {
+                let saving = full - line.net(line.quantity);
+                if saving > 0.0 {
+                    lines.push(format!("    {:<24} {:>12}", deal.name, money(-saving)));
+                }
+            }
+        }
+        lines.push(format!("  {:<26} {:>12}", "TAX", money(self.tax)));
+        if let Some(shipping) = &self.shipping {
+            let label = if shipping.express {
+                format!("shipping ({}, express)", shipping.service)
+            } else {
+                format!("shipping ({})", shipping.service)
+            };
+            lines.push(format!("  {:<26} {:>12}", label, money(shipping.cost)));
+        }
+        lines.push(format!("  {:<26} {:>12}", "TOTAL", money(self.total)));
+        for payment in &self.payments {
+            lines.push(format!(
+                "  {:<26} {:>12}",
+                payment.method,
+                money(-payment.amount)
+            ));
+        }
+        lines.push(format!("  {:<26} {:>12}", "DUE", money(self.amount_due)));
+        lines.push(format!("  {:<26} {:>12}", "POINTS EARNED", self.points));
+        lines.join("\n")
+    }
+}
+
+impl fmt::Display for Receipt {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.render(Locale::default_locale()))
+    }
+}
@@ -0,0 +1,201 @@
+// "Frequently bought together" recommendations.
+// Past receipts are kept in a plain text archive, one basket per line.
+// Mining it gives how often each product and each pair of products were
+// bought, and from those the usual association measures:
+//
+//   support(A, B)     share of all baskets that contain both A and B
+//   confidence(A → B) share of the baskets with A that also have B
+//   lift(A → B)       confidence(A → B) / share of baskets with B
+//
+// A lift above 1 means B turns up with A more often than chance.
+//
+// Names are separated by commas and quoted the way price_list.rs quotes CSV
+// fields, so "BREAD, SOURDOUGH" stays one product. Products are counted by
+// their normalised name, so "Milk" on one receipt and "MILK" on another are
+// the same product, and the cart's lines are matched the same way.
+
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::ShoppingCart;
+use crate::normalize::{NormalizeOptions, normalize_name};
+use crate::price_list::{parse_record, quote};
+use crate::receipt::Receipt;
+
+#[derive(Debug, Default)]
+pub struct ReceiptArchive {
+    baskets: Vec<Vec<String>>,
+}
+
+#[derive(Debug)]
+pub struct CoPurchaseStats {
+    baskets: usize,
+    // Every count is keyed by normalised name; `names` holds each product's
+    // name as it was first seen, for display.
+    names: HashMap<String, String>,
+    product_counts: HashMap<String, usize>,
+    // Keyed with the two names in sorted order.
+    pair_counts: HashMap<(String, String), usize>,
+}
+
+fn key(name: &str) -> String {
+    normalize_name(name, NormalizeOptions::default())
+}
+
+#[derive(Debug)]
+pub struct Recommendation {
+    pub product: String,
+    // The product already in the cart that suggested it.
+    pub because_of: String,
+    pub support: f64,
+    pub confidence: f64,
+    pub lift: f64,
+}
+
+impl ReceiptArchive {
+    pub fn load(path: impl AsRef<Path>) -> io::Result<ReceiptArchive> {
+        let text = fs::read_to_string(path)?;
+        let mut baskets = Vec::new();
+        for (index, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let names = parse_record(line).map_err(|message| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("line {}: {}", index + 1, message),
+                )
+            })?;
+            baskets.push(names.iter().map(|name| name.trim().to_string()).collect());
+        }
+        Ok(ReceiptArchive { baskets })
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let text: String = self
+            .baskets
+            .iter()
+            .map(|basket| {
+                let names: Vec<String> = basket.iter().map(|name| quote(name)).collect();
+                names.join(", ") + "\n"
+            })
+            .collect();
+        fs::write(path, text)
+    }
+
+    pub fn add(&mut self, receipt: &Receipt) {
+        let basket = receipt.lines.iter().map(|line| line.name.clone()).collect();
+        self.baskets.push(basket);
+    }
+
+    pub fn mine(&self) -> CoPurchaseStats {
+        let mut names = HashMap::new();
+        let mut product_counts = HashMap::new();
+        let mut pair_counts = HashMap::new();
+
+        for basket in &self.baskets {
+            let mut products: Vec<String> = Vec::new();
+            for name in basket {
+                let key = key(name);
+                names.entry(key.clone()).or_insert_with(|| name.clone());
+                products.push(key);
+            }
+            products.sort();
+            products.dedup();
+
+            for (index, product) in products.iter().enumerate() {
+                *product_counts.entry(product.to_string()).or_insert(0) += 1;
+                for other in &products[index + 1..] {
+                    let pair = (product.to_string(), other.to_string());
+                    *pair_counts.entry(pair).or_insert(0) += 1;
+                }
+            }
+        }
+
+        CoPurchaseStats {
+            baskets: self.baskets.len(),
+            names,
+            product_counts,
+            pair_counts,
+        }
+    }
+}
+
+impl CoPurchaseStats {
+    pub fn pair_count(&self, a: &str, b: &str) -> usize {
+        self.together(&key(a), &key(b))
+    }
+
+    // Both take normalised names.
+    fn together(&self, a: &str, b: &str) -> usize {
+        let pair = if a <= b { (a, b) } else { (b, a) };
+        self.pair_counts
+            .get(&(pair.0.to_string(), pair.1.to_string()))
+            .copied()
+            .unwrap_or(0)
+    }
+
+    fn count(&self, product: &str) -> usize {
+        self.product_counts.get(product).copied().unwrap_or(0)
+    }
+
+    // How strongly having `from` in the basket points at `to`, both
+    // normalised; `because_of` is the cart line's name for `from`.
+    fn rule(&self, from: &str, to: &str, because_of: &str) -> Option<Recommendation> {
+        let together = self.together(from, to);
+        if together == 0 {
+            return None;
+        }
+        let baskets = self.baskets as f64;
+        let confidence = together as f64 / self.count(from) as f64;
+        Some(Recommendation {
+            product: self.names.get(to).map_or(to, String::as_str).to_string(),
+            because_of: because_of.to_string(),
+            support: together as f64 / baskets,
+            confidence,
+            lift: confidence / (self.count(to) as f64 / baskets),
+        })
+    }
+
+    // The top `n` products that aren't in the cart yet, best first. Each
+    // candidate is scored by its strongest rule from anything in the cart:
+    // highest confidence, then highest lift, then by name so ties are stable.
+    pub fn recommend(&self, cart: &ShoppingCart, n: usize) -> Vec<Recommendation> {
+        let in_cart: Vec<(String, &str)> = cart
+            .item
+            .iter()
+            .map(|item| (item.normalized_name(), item.name.as_str()))
+            .collect();
+        let mut best: HashMap<&str, Recommendation> = HashMap::new();
+
+        for candidate in self.product_counts.keys() {
+            if in_cart.iter().any(|(key, _)| key == candidate) {
+                continue;
+            }
+            for (from, name) in &in_cart {
+                let Some(rule) = self.rule(from, candidate, name) else {
+                    continue;
+                };
+                let better = best.get(candidate.as_str()).is_none_or(|current| {
+                    (rule.confidence, rule.lift) > (current.confidence, current.lift)
+                });
+                if better {
+                    best.insert(candidate, rule);
+                }
+            }
+        }
+
+        let mut ranked: Vec<Recommendation> = best.into_values().collect();
+        ranked.sort_by(|a, b| {
+            b.confidence
+                .total_cmp(&a.confidence)
+                .then(b.lift.total_cmp(&a.lift))
+                .then(a.product.cmp(&b.product))
+        });
+        ranked.truncate(n);
+        ranked
+    }
+}
@@ -0,0 +1,286 @@
+// Returns are processed against the Receipt, because checkout consumed the
+// cart that produced it. The receipt remembers how much of each line has
+// already come back, so the same item can't be refunded twice.
+//
+// A refund is what the customer paid minus what the items they keep would
+// have cost on their own. So a return that breaks a multi-buy ("3 for 2",
+// two of them kept) claws back the discount the kept items no longer
+// qualify for, and tax and loyalty points are worked out again for what's
+// kept, at the rates on the receipt.
+
+use std::fmt;
+
+use crate::clock::Clock;
+use crate::gift_card::STORE_CURRENCY;
+use crate::locale::Locale;
+use crate::money::round_cents;
+use crate::policy::points_for;
+use crate::receipt::Receipt;
+
+pub const DEFAULT_RETURN_WINDOW: u64 = 30 * 24 * 60 * 60;
+
+#[derive(Debug, PartialEq)]
+pub enum ReturnError {
+    OutsideWindow,
+    UnknownLine(usize),
+    TooMany {
+        line: usize,
+        requested: u64,
+        available: u32,
+    },
+}
+
+#[derive(Debug)]
+pub struct RefundLine {
+    pub name: String,
+    pub quantity: u32,
+    // Refunded for the goods, before tax.
+    pub amount: f64,
+    // The multi-buy discount the kept items no longer qualify for, already
+    // taken off `amount`.
+    pub lost_discount: f64,
+    pub tax: f64,
+}
+
+#[derive(Debug)]
+pub struct Refund {
+    pub lines: Vec<RefundLine>,
+    pub tax: f64,
+    // Goods and tax.
+    pub total: f64,
+    // Loyalty points to take back.
+    pub points: u32,
+}
+
+impl Receipt {
+    // `returns` pairs a receipt line index with the quantity coming back;
+    // the same line may be listed more than once. The whole request is
+    // checked first, so a bad line refunds nothing.
+    pub fn process_return<C>(
+        &mut self,
+        returns: &[(usize, u32)],
+        clock: &C,
+        window: u64,
+    ) -> Result<Refund, ReturnError>
+    where
+        C: Clock,
+    {
+        if clock.now() > self.issued_at + window {
+            return Err(ReturnError::OutsideWindow);
+        }
+
+        // Quantities per line, in the order the lines were first listed.
+        let mut requests: Vec<(usize, u64)> = Vec::new();
+        for &(index, quantity) in returns {
+            if index >= self.lines.len() {
+                return Err(ReturnError::UnknownLine(index));
+            }
+            match requests.iter_mut().find(|(line, _)| *line == index) {
+                Some((_, total)) => *total += quantity as u64,
+                None => requests.push((index, quantity as u64)),
+            }
+        }
+        for &(index, requested) in &requests {
+            let available = self.lines[index].kept();
+            if requested > available as u64 {
+                return Err(ReturnError::TooMany {
+                    line: index,
+                    requested,
+                    available,
+                });
+            }
+        }
+
+        let mut lines = Vec::new();
+        for (index, quantity) in requests {
+            // Checked against `available` above, so it fits.
+            let quantity = quantity as u32;
+            let line = &mut self.lines[index];
+            let kept_before = line.kept();
+            let kept_after = kept_before - quantity;
+
+            let amount = round_cents(line.net(kept_before) - line.net(kept_after));
+            let full = round_cents(line.price * quantity as f64);
+            let tax = round_cents(line.tax(kept_before) - line.tax(kept_after));
+            line.returned += quantity;
+            lines.push(RefundLine {
+                name: line.name.clone(),
+                quantity,
+                amount,
+                lost_discount: round_cents(full - amount),
+                tax,
+            });
+        }
+
+        let goods: f64 = lines.iter().map(|line| line.amount).sum();
+        let tax = round_cents(lines.iter().map(|line| line.tax).sum());
+        let points_kept = points_for(self.goods(), self.points_per_unit);
+        let points = self.points.saturating_sub(points_kept);
+        self.points -= points;
+
+        Ok(Refund {
+            lines,
+            tax,
+            total: round_cents(goods + tax),
+            points,
+        })
+    }
+}
+
+impl Refund {
+    pub fn render(&self, locale: &Locale) -> String {
+        let money = |amount: f64| locale.format_money(amount, STORE_CURRENCY);
+        let mut lines = vec!["REFUND".to_string()];
+
+        for line in &self.lines {
+            let label = format!("{} x{}", line.name, line.quantity);
+            let full = line.amount + line.lost_discount;
+            lines.push(format!("  {:<26} {:>12}", label, money(full)));
+            if line.lost_discount > 0.0 {
+                lines.push(format!(
+                    "    {:<24} {:>12}",
+                    "offer no longer applies",
+                    money(-line.lost_discount)
+                ));
+            }
+        }
+        lines.push(format!("  {:<26} {:>12}", "TAX", money(self.tax)));
+        lines.push(format!("  {:<26} {:>12}", "TOTAL", money(self.total)));
+        lines.push(format!("  {:<26} {:>12}", "POINTS REVERSED", self.points));
+        lines.join("\n")
+    }
+}
+
+impl fmt::Display for Refund {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.render(Locale::default_locale()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::FakeClock;
+    use crate::promotions::MultiBuy;
+    use crate::receipt::ReceiptLine;
+
+    const ISSUED_AT: u64 = 1_704_475_800;
+
+    fn line(name: &str, price: f64, quantity: u32, tax_rate: f64) -> ReceiptLine {
+        ReceiptLine {
+            name: name.to_string(),
+            price,
+            list_price: price,
+            price_cut: None,
+            multi_buy: None,
+            tax_rate,
+            quantity,
+            returned: 0,
+        }
+    }
+
+    // Three bottles of milk on a 3 for 2 (2.66), tax-free, and a beer at 8%.
+    fn receipt() -> Receipt {
+        let milk = ReceiptLine {
+            multi_buy: Some(MultiBuy {
+                name: "MILK 3 FOR 2".to_string(),
+                buy: 3,
+                pay: 2,
+            }),
+            ..line("MILK", 1.33, 3, 0.0)
+        };
+        Receipt {
+            lines: vec![milk, line("BEER", 1.99, 1, 8.0)],
+            shipping: None,
+            tax: 0.16,
+            total: 4.81,
+            payments: Vec::new(),
+            amount_due: 4.81,
+            issued_at: ISSUED_AT,
+            points: 4,
+            points_per_unit: 1,
+        }
+    }
+
+    #[test]
+    fn a_return_that_breaks_a_multi_buy_claws_the_discount_back() {
+        let mut receipt = receipt();
+        let clock = FakeClock::new(ISSUED_AT + 86_400);
+
+        // The two bottles kept cost 2.66 on their own, what all three did.
+        let refund = receipt
+            .process_return(&[(0, 1)], &clock, DEFAULT_RETURN_WINDOW)
+            .unwrap();
+        assert_eq!(refund.lines[0].amount, 0.0);
+        assert_eq!(refund.lines[0].lost_discount, 1.33);
+        assert_eq!(refund.total, 0.0);
+        assert_eq!(refund.points, 0);
+
+        let refund = receipt
+            .process_return(&[(1, 1)], &clock, DEFAULT_RETURN_WINDOW)
+            .unwrap();
+        assert_eq!((refund.lines[0].amount, refund.tax), (1.99, 0.16));
+        assert_eq!(refund.total, 2.15);
+        // 2.66 kept earns 2 of the 4 points.
+        assert_eq!(refund.points, 2);
+        assert_eq!(receipt.points, 2);
+    }
+
+    #[test]
+    fn a_line_can_come_back_in_parts_but_not_twice() {
+        let mut receipt = receipt();
+        let clock = FakeClock::new(ISSUED_AT);
+
+        receipt
+            .process_return(&[(0, 1)], &clock, DEFAULT_RETURN_WINDOW)
+            .unwrap();
+        let refund = receipt
+            .process_return(&[(0, 1)], &clock, DEFAULT_RETURN_WINDOW)
+            .unwrap();
+        assert_eq!(refund.lines[0].amount, 1.33);
+        assert_eq!(refund.lines[0].lost_discount, 0.0);
+
+        assert_eq!(
+            receipt
+                .process_return(&[(0, 1), (0, 1)], &clock, DEFAULT_RETURN_WINDOW)
+                .unwrap_err(),
+            ReturnError::TooMany {
+                line: 0,
+                requested: 2,
+                available: 1
+            }
+        );
+        assert_eq!(
+            receipt
+                .process_return(&[(0, u32::MAX), (0, 2)], &clock, DEFAULT_RETURN_WINDOW)
+                .unwrap_err(),
+            ReturnError::TooMany {
+                line: 0,
+                requested: u32::MAX as u64 + 2,
+                available: 1
+            }
+        );
+        assert_eq!(receipt.lines[0].returned, 2);
+    }
+
+    #[test]
+    fn returns_outside_the_window_or_for_unknown_lines_are_refused() {
+        let mut receipt = receipt();
+        let clock = FakeClock::new(ISSUED_AT + DEFAULT_RETURN_WINDOW + 1);
+        assert_eq!(
+            receipt
+                .process_return(&[(0, 1)], &clock, DEFAULT_RETURN_WINDOW)
+                .unwrap_err(),
+            ReturnError::OutsideWindow
+        );
+
+        clock.set(ISSUED_AT);
+        assert_eq!(
+            receipt
+                .process_return(&[(1, 1), (5, 1)], &clock, DEFAULT_RETURN_WINDOW)
+                .unwrap_err(),
+            ReturnError::UnknownLine(5)
+        );
+        assert_eq!(receipt.lines[1].returned, 0);
+    }
+}
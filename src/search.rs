@@ -0,0 +1,211 @@
+// Fuzzy product search for the till.
+// Names (and any aliases) are normalised, split into tokens and indexed by
+// token in a BTreeMap, so that:
+//
+//   - an exact token is a map lookup
+//   - a prefix ("banan") is a range scan over the sorted tokens
+//   - a typo ("aple", "choclate") is a bounded edit-distance check against
+//     the distinct tokens (and their beginnings, so a misspelt prefix still
+//     matches). Only tokens starting with the query's first or second
+//     letter are checked: staff rarely get the first letter wrong, and
+//     that keeps typo matching fast on a 100k product catalog
+//
+// Every query token must match something for a product to be returned.
+// Results are ranked by how well the tokens matched.
+
+use std::collections::{BTreeMap, HashMap};
+
+use crate::ShoppingCart;
+use crate::catalog::Catalog;
+use crate::normalize::{NormalizeOptions, normalize_name};
+
+const EXACT: u32 = 3;
+const PREFIX: u32 = 2;
+const TYPO: u32 = 1;
+
+#[derive(Debug)]
+pub struct SearchHit {
+    // A SKU when searching the catalog, a line index when searching a cart.
+    pub key: String,
+    pub name: String,
+    pub score: u32,
+}
+
+#[derive(Debug, Default)]
+pub struct SearchIndex {
+    documents: Vec<(String, String)>,
+    by_key: HashMap<String, usize>,
+    tokens: BTreeMap<String, Vec<usize>>,
+    by_initial: HashMap<char, Vec<String>>,
+}
+
+fn tokenize(text: &str) -> Vec<String> {
+    let options = NormalizeOptions {
+        strip_accents: true,
+    };
+    normalize_name(text, options)
+        .split(|ch: char| !ch.is_alphanumeric())
+        .filter(|token| !token.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+// Levenshtein distance between `a` and `b`, or None as soon as it's
+// certain to be more than `max`.
+fn edit_distance(a: &[char], b: &[char], max: usize) -> Option<usize> {
+    if a.len().abs_diff(b.len()) > max {
+        return None;
+    }
+
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.iter().enumerate() {
+        let mut current = vec![i + 1; b.len() + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != cb);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        if current.iter().min().is_some_and(|best| *best > max) {
+            return None;
+        }
+        previous = current;
+    }
+    Some(previous[b.len()]).filter(|distance| *distance <= max)
+}
+
+impl SearchIndex {
+    pub fn from_catalog(catalog: &Catalog) -> SearchIndex {
+        let mut index = SearchIndex::default();
+        for entry in catalog.entries() {
+            index.add(&entry.sku, &entry.item.name);
+        }
+        index
+    }
+
+    pub fn from_cart(cart: &ShoppingCart) -> SearchIndex {
+        let mut index = SearchIndex::default();
+        for (line, item) in cart.item.iter().enumerate() {
+            index.add(&line.to_string(), &item.name);
+        }
+        index
+    }
+
+    fn add(&mut self, key: &str, name: &str) {
+        let document = self.documents.len();
+        self.documents.push((key.to_string(), name.to_string()));
+        self.by_key.insert(key.to_string(), document);
+        self.index_tokens(document, name);
+    }
+
+    // Lets "cola" find the product listed as "SODA", for example.
+    pub fn add_alias(&mut self, key: &str, alias: &str) -> bool {
+        match self.by_key.get(key) {
+            Some(&document) => {
+                self.index_tokens(document, alias);
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn index_tokens(&mut self, document: usize, text: &str) {
+        for token in tokenize(text) {
+            if !self.tokens.contains_key(&token) {
+                let initial = token.chars().next().expect("tokens aren't empty");
+                self.by_initial
+                    .entry(initial)
+                    .or_default()
+                    .push(token.clone());
+            }
+            let documents = self.tokens.entry(token).or_default();
+            if documents.last() != Some(&document) {
+                documents.push(document);
+            }
+        }
+    }
+
+    // The best score each document gets for one query token.
+    fn match_token(&self, query: &str) -> HashMap<usize, u32> {
+        let mut scores: HashMap<usize, u32> = HashMap::new();
+        let mut award = |documents: &Vec<usize>, score: u32| {
+            for &document in documents {
+                let best = scores.entry(document).or_insert(0);
+                *best = (*best).max(score);
+            }
+        };
+
+        for (token, documents) in self.tokens.range(query.to_string()..) {
+            if !token.starts_with(query) {
+                break;
+            }
+            award(documents, if token == query { EXACT } else { PREFIX });
+        }
+
+        let query: Vec<char> = query.chars().collect();
+        let max_typos = if query.len() <= 4 { 1 } else { 2 };
+        let mut initials = query.iter().take(2).copied().collect::<Vec<_>>();
+        initials.dedup();
+        let candidates = initials
+            .iter()
+            .filter_map(|initial| self.by_initial.get(initial))
+            .flatten();
+
+        let mut chars = Vec::new();
+        for token in candidates {
+            // Only as much of the token as a misspelt prefix could cover.
+            chars.clear();
+            chars.extend(token.chars().take(query.len() + max_typos));
+            if chars.len() + max_typos < query.len() {
+                continue;
+            }
+            let whole = edit_distance(&query, &chars, max_typos);
+            let prefix = (query.len()..=chars.len())
+                .any(|end| edit_distance(&query, &chars[..end], max_typos).is_some());
+            if whole.is_some() || prefix {
+                award(&self.tokens[token], TYPO);
+            }
+        }
+        scores
+    }
+
+    pub fn search(&self, query: &str, limit: usize) -> Vec<SearchHit> {
+        let query_tokens = tokenize(query);
+        if query_tokens.is_empty() {
+            return Vec::new();
+        }
+
+        let mut totals: HashMap<usize, u32> = HashMap::new();
+        for (position, token) in query_tokens.iter().enumerate() {
+            let matches = self.match_token(token);
+            if position == 0 {
+                totals = matches;
+            } else {
+                totals.retain(|document, _| matches.contains_key(document));
+                for (document, total) in totals.iter_mut() {
+                    *total += matches[document];
+                }
+            }
+        }
+
+        let mut hits: Vec<SearchHit> = totals
+            .into_iter()
+            .map(|(document, score)| {
+                let (key, name) = &self.documents[document];
+                SearchHit {
+                    key: key.clone(),
+                    name: name.clone(),
+                    score,
+                }
+            })
+            .collect();
+        // Best score first; shorter names are closer matches; then by key
+        // so equal hits always come back in the same order.
+        hits.sort_by(|a, b| {
+            b.score
+                .cmp(&a.score)
+                .then(a.name.len().cmp(&b.name.len()))
+                .then(a.key.cmp(&b.key))
+        });
+        hits.truncate(limit);
+        hits
+    }
+}
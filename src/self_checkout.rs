@@ -0,0 +1,214 @@
+// Self-checkout lanes.
+// A SelfCheckout session wraps a ShoppingCart and keeps a running total of
+// what the bagging area should weigh, from the weight of every item
+// scanned. The scale itself is a closure returning its current reading in
+// kg, so it can be a real device or a simulation.
+//
+// When the reading doesn't match, an intervention is raised and the cart
+// can't be released for checkout until an attendant has cleared it. An
+// item with no known weight (weight_kg 0, and no weight in its barcode)
+// raises one as soon as it's scanned, as the scale can't vouch for it.
+// Nothing more can be scanned while an intervention is open, so a new one
+// can never hide one the attendant hasn't seen yet.
+
+use crate::ShoppingCart;
+use crate::catalog::Catalog;
+use crate::error::CartError;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Intervention {
+    // More weight in the bagging area than was scanned.
+    UnexpectedItem { expected_kg: f64, measured_kg: f64 },
+    // Something scanned hasn't been put in the bagging area.
+    ItemNotBagged { expected_kg: f64, measured_kg: f64 },
+    // Scanned, but with no weight to check the bagging area against.
+    UnknownWeight { name: String },
+}
+
+pub struct SelfCheckout<S>
+where
+    S: Fn() -> f64,
+{
+    cart: ShoppingCart,
+    scale: S,
+    expected_kg: f64,
+    tolerance_kg: f64,
+    pending: Option<Intervention>,
+}
+
+impl<S> SelfCheckout<S>
+where
+    S: Fn() -> f64,
+{
+    pub fn start(cart: ShoppingCart, scale: S, tolerance_kg: f64) -> SelfCheckout<S> {
+        let expected_kg = cart.weight_kg();
+        SelfCheckout {
+            cart,
+            scale,
+            expected_kg,
+            tolerance_kg,
+            pending: None,
+        }
+    }
+
+    // The bagging area is checked first: a scan is refused while it doesn't
+    // match or any other intervention is open.
+    pub fn scan(&mut self, raw: &str, catalog: &Catalog) -> Result<(), CartError> {
+        if self.pending.is_none() {
+            self.check_weight();
+        }
+        if let Some(intervention) = &self.pending {
+            return Err(CartError::InterventionRequired(intervention.clone()));
+        }
+        self.cart.scan(raw, catalog)?;
+        let item = self.cart.item.last().expect("scan just added an item");
+        if item.weight_kg > 0.0 {
+            self.expected_kg += item.weight_kg * item.quantity as f64;
+        } else {
+            self.pending = Some(Intervention::UnknownWeight {
+                name: item.name.clone(),
+            });
+        }
+        Ok(())
+    }
+
+    // Compares the scale with what's been scanned. Called once the shopper
+    // has bagged an item; any mismatch becomes the pending intervention.
+    // An item of unknown weight stays pending whatever the scale says.
+    pub fn check_weight(&mut self) -> Option<&Intervention> {
+        if let Some(Intervention::UnknownWeight { .. }) = self.pending {
+            return self.pending.as_ref();
+        }
+        let measured_kg = (self.scale)();
+        let expected_kg = self.expected_kg;
+
+        self.pending = if measured_kg > expected_kg + self.tolerance_kg {
+            Some(Intervention::UnexpectedItem {
+                expected_kg,
+                measured_kg,
+            })
+        } else if measured_kg < expected_kg - self.tolerance_kg {
+            Some(Intervention::ItemNotBagged {
+                expected_kg,
+                measured_kg,
+            })
+        } else {
+            None
+        };
+        self.pending.as_ref()
+    }
+
+    // The attendant looks at the intervention and decides. If `attendant`
+    // approves, the current reading is accepted as correct from now on.
+    // Returns whether the lane is clear.
+    pub fn attendant_override<F>(&mut self, attendant: F) -> bool
+    where
+        F: FnOnce(&Intervention) -> bool,
+    {
+        if let Some(intervention) = &self.pending
+            && attendant(intervention)
+        {
+            self.expected_kg = (self.scale)();
+            self.pending = None;
+        }
+        self.pending.is_none()
+    }
+
+    // Hands the cart back for checkout, once the scale agrees with it and
+    // no intervention is left open.
+    pub fn finish(mut self) -> Result<ShoppingCart, CartError> {
+        if self.pending.is_none() {
+            self.check_weight();
+        }
+        match self.pending {
+            Some(intervention) => Err(CartError::InterventionRequired(intervention)),
+            None => Ok(self.cart),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SupermarketItem;
+    use std::cell::Cell;
+
+    fn catalog() -> Catalog {
+        let mut catalog = Catalog::new();
+        catalog
+            .add("1006", SupermarketItem::new("LOOSE APPLES", 2.40).unwrap())
+            .unwrap();
+        catalog.add_barcode("2712340012503", "1006").unwrap();
+        catalog
+            .add("1004", SupermarketItem::new("SODA", 0.99).unwrap())
+            .unwrap();
+        catalog.add_barcode("96385074", "1004").unwrap();
+        catalog
+    }
+
+    #[test]
+    fn an_open_intervention_cant_be_replaced_by_a_scan() {
+        let catalog = catalog();
+        let bagging_area = Cell::new(0.0);
+        let cart = ShoppingCart::new(Vec::new()).unwrap();
+        let mut lane = SelfCheckout::start(cart, || bagging_area.get(), 0.05);
+
+        lane.scan("2712340012503", &catalog).unwrap();
+        // The apples go in the bag, and so does an unscanned 3 kg item.
+        bagging_area.set(1.25 + 3.0);
+        let raised = Intervention::UnexpectedItem {
+            expected_kg: 1.25,
+            measured_kg: 4.25,
+        };
+        assert_eq!(lane.check_weight(), Some(&raised));
+
+        assert!(matches!(
+            lane.scan("96385074", &catalog),
+            Err(CartError::InterventionRequired(ref open)) if *open == raised
+        ));
+        assert_eq!(lane.cart.item.len(), 1);
+        let seen = Cell::new(None);
+        lane.attendant_override(|intervention| {
+            seen.set(Some(intervention.clone()));
+            false
+        });
+        assert_eq!(seen.take(), Some(raised));
+        assert!(lane.finish().is_err());
+    }
+
+    #[test]
+    fn an_unbagged_mismatch_is_caught_at_the_next_scan() {
+        let catalog = catalog();
+        let bagging_area = Cell::new(0.0);
+        let cart = ShoppingCart::new(Vec::new()).unwrap();
+        let mut lane = SelfCheckout::start(cart, || bagging_area.get(), 0.05);
+
+        bagging_area.set(3.0);
+        assert!(matches!(
+            lane.scan("96385074", &catalog),
+            Err(CartError::InterventionRequired(
+                Intervention::UnexpectedItem { .. }
+            ))
+        ));
+    }
+
+    #[test]
+    fn an_item_of_unknown_weight_needs_an_attendant() {
+        let catalog = catalog();
+        let bagging_area = Cell::new(0.0);
+        let cart = ShoppingCart::new(Vec::new()).unwrap();
+        let mut lane = SelfCheckout::start(cart, || bagging_area.get(), 0.05);
+
+        lane.scan("96385074", &catalog).unwrap();
+        bagging_area.set(0.35);
+        assert_eq!(
+            lane.check_weight(),
+            Some(&Intervention::UnknownWeight {
+                name: "SODA".to_string()
+            })
+        );
+        assert!(lane.scan("96385074", &catalog).is_err());
+        assert!(lane.attendant_override(|_| true));
+        assert_eq!(lane.finish().unwrap().item.len(), 1);
+    }
+}
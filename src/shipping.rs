@@ -0,0 +1,217 @@
+// Shipping quotes for a cart.
+// Rates are grouped per zone and loaded from a plain text config file (see
+// shipping_rates.conf in the repo root). Every service whose weight and
+// size limits fit the cart is offered both standard and express; the
+// cheapest one is picked out but all of them are returned.
+
+use std::fs;
+
+use crate::ShoppingCart;
+use crate::money::round_cents;
+
+#[derive(Debug)]
+pub struct Rate {
+    pub zone: String,
+    pub service: String,
+    pub base: f64,
+    pub per_kg: f64,
+    pub max_weight_kg: f64,
+    pub max_length_cm: f64,
+}
+
+#[derive(Debug)]
+pub struct ShippingConfig {
+    pub free_over: f64,
+    pub express_surcharge: f64,
+    pub rates: Vec<Rate>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ShippingOption {
+    pub service: String,
+    pub express: bool,
+    pub cost: f64,
+}
+
+#[derive(Debug)]
+pub struct ShippingQuote {
+    pub cheapest: ShippingOption,
+    // Every valid option, cheapest first (including `cheapest` itself).
+    pub options: Vec<ShippingOption>,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum ShippingError {
+    Config { line: usize, message: String },
+    Io(String),
+    UnknownZone(String),
+    NoValidOption,
+}
+
+impl ShippingConfig {
+    pub fn load(path: &str) -> Result<ShippingConfig, ShippingError> {
+        let text =
+            fs::read_to_string(path).map_err(|error| ShippingError::Io(error.to_string()))?;
+        ShippingConfig::parse(&text)
+    }
+
+    pub fn parse(text: &str) -> Result<ShippingConfig, ShippingError> {
+        let mut config = ShippingConfig {
+            free_over: f64::INFINITY,
+            express_surcharge: 0.0,
+            rates: Vec::new(),
+        };
+
+        for (index, line) in text.lines().enumerate() {
+            let line_number = index + 1;
+            let error = |message: &str| ShippingError::Config {
+                line: line_number,
+                message: message.to_string(),
+            };
+            // Prices and limits alike must be finite and not negative, as
+            // gift_card.rs checks amounts.
+            let number = |field: &str| match field.parse::<f64>() {
+                Ok(value) if value.is_finite() && value >= 0.0 => Ok(value),
+                Ok(_) => Err(error("expected a finite number, 0 or more")),
+                Err(_) => Err(error("expected a number")),
+            };
+
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            if let Some((key, value)) = line.split_once('=') {
+                match key.trim() {
+                    "free_over" => config.free_over = number(value.trim())?,
+                    "express_surcharge" => config.express_surcharge = number(value.trim())?,
+                    _ => return Err(error("unknown setting")),
+                }
+                continue;
+            }
+
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            match fields.as_slice() {
+                ["rate", zone, service, base, per_kg, max_weight, max_length] => {
+                    config.rates.push(Rate {
+                        zone: zone.to_string(),
+                        service: service.to_string(),
+                        base: number(base)?,
+                        per_kg: number(per_kg)?,
+                        max_weight_kg: number(max_weight)?,
+                        max_length_cm: number(max_length)?,
+                    })
+                }
+                _ => {
+                    return Err(error(
+                        "expected `rate <zone> <service> <base> <per_kg> <max_kg> <max_cm>`",
+                    ));
+                }
+            }
+        }
+
+        Ok(config)
+    }
+}
+
+impl ShoppingCart {
+    pub fn weight_kg(&self) -> f64 {
+        self.item
+            .iter()
+            .map(|item| item.weight_kg * item.quantity as f64)
+            .sum()
+    }
+
+    // The longest side of any single item decides which services can carry it.
+    fn longest_side_cm(&self) -> f64 {
+        self.item
+            .iter()
+            .flat_map(|item| item.dimensions_cm)
+            .fold(0.0, f64::max)
+    }
+
+    pub fn quote_shipping(
+        &self,
+        config: &ShippingConfig,
+        zone: &str,
+    ) -> Result<ShippingQuote, ShippingError> {
+        let zone_rates: Vec<&Rate> = config
+            .rates
+            .iter()
+            .filter(|rate| rate.zone == zone)
+            .collect();
+        if zone_rates.is_empty() {
+            return Err(ShippingError::UnknownZone(zone.to_string()));
+        }
+
+        let weight = self.weight_kg();
+        let longest = self.longest_side_cm();
+        let ships_free = self.total() >= config.free_over;
+        let mut options = Vec::new();
+
+        for rate in zone_rates {
+            if weight > rate.max_weight_kg || longest > rate.max_length_cm {
+                continue;
+            }
+            let cost = round_cents(rate.base + rate.per_kg * weight);
+            options.push(ShippingOption {
+                service: rate.service.clone(),
+                express: false,
+                cost: if ships_free { 0.0 } else { cost },
+            });
+            options.push(ShippingOption {
+                service: rate.service.clone(),
+                express: true,
+                cost: round_cents(cost + config.express_surcharge),
+            });
+        }
+
+        options.sort_by(|a, b| a.cost.total_cmp(&b.cost));
+        let cheapest = options
+            .first()
+            .cloned()
+            .ok_or(ShippingError::NoValidOption)?;
+        Ok(ShippingQuote { cheapest, options })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config_error(text: &str) -> ShippingError {
+        ShippingConfig::parse(text).unwrap_err()
+    }
+
+    #[test]
+    fn non_finite_and_negative_values_are_refused() {
+        for text in [
+            "rate d s NaN 1 10 10",
+            "rate d s 1 -5 10 10",
+            "rate d s 1 1 inf 10",
+            "free_over = -1",
+            "express_surcharge = inf",
+        ] {
+            assert_eq!(
+                config_error(&format!("# rates\n{}", text)),
+                ShippingError::Config {
+                    line: 2,
+                    message: "expected a finite number, 0 or more".to_string()
+                },
+                "{}",
+                text
+            );
+        }
+        assert!(matches!(
+            config_error("free_over = lots"),
+            ShippingError::Config { line: 1, .. }
+        ));
+    }
+
+    #[test]
+    fn the_bundled_rates_load() {
+        let config = ShippingConfig::parse(include_str!("../shipping_rates.conf")).unwrap();
+        assert_eq!(config.free_over, 50.0);
+        assert_eq!(config.rates.len(), 4);
+    }
+}
@@ -0,0 +1,362 @@
+// Shopping lists typed up as plain text, one product per line:
+//
+//   2x apples
+//   1.5 kg bananas
+//   milk 2L
+//
+// An amount can lead the line ("2x", "2 x", "2", "1.5 kg", "500g") or
+// trail it ("x3", "2L", "2 l"); whatever is left is looked up with the
+// fuzzy search index. Lines that match nothing, or match several products
+// equally well, are reported with their line number instead of guessing.
+// A count must be a whole number that fits a quantity: "2.5 x soda" or
+// "1e9 x soda" is reported rather than rounded. Blank lines and lines
+// starting with `#` are skipped.
+
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+use crate::catalog::Catalog;
+use crate::error::ItemError;
+use crate::search::SearchIndex;
+use crate::unit_price::{Measure, PackSize, Unit};
+use crate::{ShoppingCart, SupermarketItem};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Amount {
+    Count(u32),
+    Measure(PackSize),
+    // A number where a count goes that isn't one, e.g. "2.5" or "1e9".
+    BadCount(String),
+}
+
+#[derive(Debug)]
+pub struct ListLine {
+    pub line: usize,
+    pub amount: Option<Amount>,
+    pub query: String,
+}
+
+#[derive(Debug, Default)]
+pub struct ShoppingList {
+    pub lines: Vec<ListLine>,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum ListProblem {
+    Unknown(String),
+    // Every product that matched the query equally well.
+    Ambiguous {
+        query: String,
+        candidates: Vec<String>,
+    },
+    // "2 l bananas": a measure the product isn't sold in.
+    UnitMismatch {
+        query: String,
+        name: String,
+    },
+    ZeroAmount(String),
+    InvalidCount {
+        query: String,
+        count: String,
+    },
+    // The amount left the product invalid, e.g. a weight too big to price.
+    Invalid {
+        query: String,
+        error: ItemError,
+    },
+}
+
+#[derive(Debug)]
+pub struct ImportReport {
+    pub cart: ShoppingCart,
+    // The list line each cart line came from.
+    pub sources: Vec<usize>,
+    pub problems: Vec<(usize, ListProblem)>,
+}
+
+// Splits "1.5kg" or "2L" into a number and a unit.
+fn glued_measure(token: &str) -> Option<PackSize> {
+    let split = token.find(|c: char| c.is_alphabetic())?;
+    let (number, unit) = token.split_at(split);
+    let amount = number.parse().ok()?;
+    Some(PackSize::new(amount, unit.parse::<Unit>().ok()?))
+}
+
+// Counts are parsed as integers; anything else that still reads as a
+// number is a bad count rather than something to round.
+fn count_of(number: &str) -> Option<Amount> {
+    if let Ok(n) = number.parse() {
+        return Some(Amount::Count(n));
+    }
+    number
+        .parse::<f64>()
+        .ok()
+        .map(|_| Amount::BadCount(number.to_string()))
+}
+
+// "2x" or "x2".
+fn count(token: &str) -> Option<Amount> {
+    let lower = token.to_lowercase();
+    let number = lower
+        .strip_suffix('x')
+        .or_else(|| lower.strip_prefix('x'))?;
+    count_of(number)
+}
+
+fn leading_amount(words: &[&str]) -> Option<(Amount, usize)> {
+    let first = words.first()?;
+    if let Some(amount) = count(first) {
+        return Some((amount, 1));
+    }
+    if let Some(pack) = glued_measure(first) {
+        return Some((Amount::Measure(pack), 1));
+    }
+
+    let number: f64 = first.parse().ok()?;
+    match words.get(1) {
+        Some(word) if word.eq_ignore_ascii_case("x") => Some((count_of(first)?, 2)),
+        Some(word) if word.parse::<Unit>().is_ok() && words.len() > 2 => {
+            let unit = word.parse().ok()?;
+            Some((Amount::Measure(PackSize::new(number, unit)), 2))
+        }
+        _ => count_of(first).map(|amount| (amount, 1)),
+    }
+}
+
+fn trailing_amount(words: &[&str]) -> Option<(Amount, usize)> {
+    let (last, rest) = words.split_last()?;
+    if let Some(amount) = count(last) {
+        return Some((amount, 1));
+    }
+    if let Some(pack) = glued_measure(last) {
+        return Some((Amount::Measure(pack), 1));
+    }
+
+    let unit = last.parse().ok()?;
+    let number = rest.last()?.parse().ok()?;
+    Some((Amount::Measure(PackSize::new(number, unit)), 2))
+}
+
+impl ShoppingList {
+    pub fn load(path: impl AsRef<Path>) -> std::io::Result<ShoppingList> {
+        Ok(ShoppingList::parse(&fs::read_to_string(path)?))
+    }
+
+    pub fn parse(text: &str) -> ShoppingList {
+        let mut list = ShoppingList::default();
+
+        for (index, line) in text.lines().enumerate() {
+            // Lists are often bulleted.
+            let line = line.trim().trim_start_matches(['-', '*']).trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let words: Vec<&str> = line.split_whitespace().collect();
+            let (amount, query) = if let Some((amount, used)) = leading_amount(&words) {
+                (Some(amount), &words[used..])
+            } else if let Some((amount, used)) = trailing_amount(&words) {
+                (Some(amount), &words[..words.len() - used])
+            } else {
+                (None, &words[..])
+            };
+
+            list.lines.push(ListLine {
+                line: index + 1,
+                amount,
+                query: query.join(" "),
+            });
+        }
+
+        list
+    }
+
+    // Looks every line up in `index` (which may carry aliases) and builds
+    // a cart from the catalog products it resolves to.
+    pub fn import(&self, catalog: &Catalog, index: &SearchIndex) -> ImportReport {
+        let mut items = Vec::new();
+        let mut sources = Vec::new();
+        let mut problems = Vec::new();
+
+        for line in &self.lines {
+            let hits = index.search(&line.query, 5);
+            let Some(best) = hits.first() else {
+                problems.push((line.line, ListProblem::Unknown(line.query.clone())));
+                continue;
+            };
+            let tied: Vec<String> = hits
+                .iter()
+                .filter(|hit| hit.score == best.score)
+                .map(|hit| hit.name.clone())
+                .collect();
+            if tied.len() > 1 {
+                let problem = ListProblem::Ambiguous {
+                    query: line.query.clone(),
+                    candidates: tied,
+                };
+                problems.push((line.line, problem));
+                continue;
+            }
+            let Some(entry) = catalog.get(&best.key) else {
+                problems.push((line.line, ListProblem::Unknown(line.query.clone())));
+                continue;
+            };
+
+            match apply_amount(entry.item.clone(), line.amount.clone(), &line.query) {
+                Ok(item) => {
+                    items.push(item);
+                    sources.push(line.line);
+                }
+                Err(problem) => problems.push((line.line, problem)),
+            }
+        }
+
+        ImportReport {
+            cart: ShoppingCart::new(items).expect("apply_amount validated every line"),
+            sources,
+            problems,
+        }
+    }
+}
+
+// A count is a quantity. A measure buys enough packs to cover it, or, for
+// a product without a pack size, is weighed out at its price per kg.
+fn apply_amount(
+    mut item: SupermarketItem,
+    amount: Option<Amount>,
+    query: &str,
+) -> Result<SupermarketItem, ListProblem> {
+    match amount {
+        None => {}
+        Some(Amount::Count(0)) => return Err(ListProblem::ZeroAmount(query.to_string())),
+        Some(Amount::Count(n)) => item.quantity = n,
+        Some(Amount::BadCount(count)) => {
+            return Err(ListProblem::InvalidCount {
+                query: query.to_string(),
+                count,
+            });
+        }
+        Some(Amount::Measure(wanted)) => {
+            let (measure, base) = wanted.base();
+            if base.is_nan() || base <= 0.0 {
+                return Err(ListProblem::ZeroAmount(query.to_string()));
+            }
+            match item.pack.map(|pack| pack.base()) {
+                Some((pack_measure, pack_base)) if pack_measure == measure => {
+                    item.quantity = (base / pack_base).ceil() as u32;
+                }
+                None if measure == Measure::Mass => item.price_by_weight(base / 1000.0),
+                _ => {
+                    return Err(ListProblem::UnitMismatch {
+                        query: query.to_string(),
+                        name: item.name,
+                    });
+                }
+            }
+        }
+    }
+    item.validate().map_err(|error| ListProblem::Invalid {
+        query: query.to_string(),
+        error,
+    })?;
+    Ok(item)
+}
+
+impl fmt::Display for ListProblem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ListProblem::Unknown(query) => write!(f, "no product matches `{}`", query),
+            ListProblem::Ambiguous { query, candidates } => {
+                write!(f, "`{}` could be {}", query, candidates.join(" or "))
+            }
+            ListProblem::UnitMismatch { query, name } => {
+                write!(f, "`{}`: {} isn't sold in that unit", query, name)
+            }
+            ListProblem::ZeroAmount(query) => write!(f, "`{}`: amount must be above zero", query),
+            ListProblem::InvalidCount { query, count } => {
+                write!(f, "`{}`: `{}` isn't a whole number of items", query, count)
+            }
+            ListProblem::Invalid { query, error } => write!(f, "`{}`: {}", query, error),
+        }
+    }
+}
+
+impl fmt::Display for ImportReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (item, line) in self.cart.item.iter().zip(&self.sources) {
+            writeln!(
+                f,
+                "line {:>3}: {} x{} {:.2}",
+                line, item.name, item.quantity, item.price
+            )?;
+        }
+        for (line, problem) in &self.problems {
+            writeln!(f, "line {:>3}: {}", line, problem)?;
+        }
+        write!(
+            f,
+            "{} added, {} to check, total {:.2}",
+            self.cart.item.len(),
+            self.problems.len(),
+            self.cart.total()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn amounts(text: &str) -> Vec<(Option<Amount>, String)> {
+        ShoppingList::parse(text)
+            .lines
+            .into_iter()
+            .map(|line| (line.amount, line.query))
+            .collect()
+    }
+
+    #[test]
+    fn counts_must_be_whole_numbers_that_fit() {
+        let bad = |count: &str| Some(Amount::BadCount(count.to_string()));
+        assert_eq!(
+            amounts("2.5 x soda\n1e9 x soda\nnan x soda\n5000000000 soda\nsoda x2.5\n1.5x soda"),
+            vec![
+                (bad("2.5"), "soda".to_string()),
+                (bad("1e9"), "soda".to_string()),
+                (bad("nan"), "soda".to_string()),
+                (bad("5000000000"), "soda".to_string()),
+                (bad("2.5"), "soda".to_string()),
+                (bad("1.5"), "soda".to_string()),
+            ]
+        );
+        assert_eq!(
+            amounts("2 x soda\n3 soda\nsoda x4"),
+            vec![
+                (Some(Amount::Count(2)), "soda".to_string()),
+                (Some(Amount::Count(3)), "soda".to_string()),
+                (Some(Amount::Count(4)), "soda".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn a_bad_count_is_reported_not_rounded() {
+        let soda = SupermarketItem::new("SODA", 0.99).unwrap();
+        assert_eq!(
+            apply_amount(soda, Some(Amount::BadCount("2.5".to_string())), "soda").err(),
+            Some(ListProblem::InvalidCount {
+                query: "soda".to_string(),
+                count: "2.5".to_string(),
+            })
+        );
+        assert_eq!(
+            ListProblem::InvalidCount {
+                query: "soda".to_string(),
+                count: "1e9".to_string(),
+            }
+            .to_string(),
+            "`soda`: `1e9` isn't a whole number of items"
+        );
+    }
+}
@@ -0,0 +1,255 @@
+// Splitting one receipt's bill between several payers.
+// The bill is the receipt total: goods after promotions, tax and shipping.
+// Every strategy boils down to a weight per payer; the total is then shared
+// out in whole cents and any leftover cents go to the payers with the
+// largest fractional share (earliest payer first on a tie), so the shares
+// always add back up to the cart total and the same input always gives the
+// same answer.
+//
+// A payer's weight is what their lines cost after promotions, plus those
+// lines' own tax, so discounts and tax follow the lines they belong to and
+// shipping is shared in proportion.
+
+use std::fmt;
+
+use crate::receipt::{Receipt, ReceiptLine};
+
+#[derive(Debug)]
+pub struct PayerShare {
+    pub payer: String,
+    pub amount: f64,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum SplitError {
+    NoPayers,
+    // Negative, NaN or infinite.
+    InvalidShare { payer: String, percent: f64 },
+    PercentagesDontAddUp(f64),
+    LineCountMismatch { lines: usize, payers: usize },
+}
+
+// When the weights add up to nothing (every line free, but there's
+// shipping), the payers share the total equally.
+fn allocate(total: f64, mut weights: Vec<(String, f64)>) -> Result<Vec<PayerShare>, SplitError> {
+    if weights.is_empty() {
+        return Err(SplitError::NoPayers);
+    }
+    let mut weight_sum: f64 = weights.iter().map(|(_, weight)| weight).sum();
+    if weight_sum <= 0.0 {
+        weights.iter_mut().for_each(|(_, weight)| *weight = 1.0);
+        weight_sum = weights.len() as f64;
+    }
+
+    let total_cents = (total * 100.0).round() as i64;
+    let mut cents = Vec::new();
+    let mut fractions = Vec::new();
+
+    for (index, (_, weight)) in weights.iter().enumerate() {
+        let exact = total_cents as f64 * weight / weight_sum;
+        cents.push(exact.floor() as i64);
+        fractions.push((index, exact - exact.floor()));
+    }
+
+    // Largest fraction first; the stable sort keeps earlier payers ahead on ties.
+    fractions.sort_by(|a, b| b.1.total_cmp(&a.1));
+    let leftover = total_cents - cents.iter().sum::<i64>();
+    for &(index, _) in fractions.iter().take(leftover as usize) {
+        cents[index] += 1;
+    }
+
+    Ok(weights
+        .into_iter()
+        .zip(cents)
+        .map(|((payer, _), cents)| PayerShare {
+            payer,
+            amount: cents as f64 / 100.0,
+        })
+        .collect())
+}
+
+impl Receipt {
+    pub fn split_evenly(&self, payers: &[&str]) -> Result<Vec<PayerShare>, SplitError> {
+        let weights = payers
+            .iter()
+            .map(|payer| (payer.to_string(), 1.0))
+            .collect();
+        allocate(self.total, weights)
+    }
+
+    // `shares` are percentages and must add up to 100.
+    pub fn split_by_percentage(
+        &self,
+        shares: &[(&str, f64)],
+    ) -> Result<Vec<PayerShare>, SplitError> {
+        if let Some(&(payer, percent)) = shares
+            .iter()
+            .find(|(_, percent)| !percent.is_finite() || *percent < 0.0)
+        {
+            return Err(SplitError::InvalidShare {
+                payer: payer.to_string(),
+                percent,
+            });
+        }
+        let sum: f64 = shares.iter().map(|(_, percent)| percent).sum();
+        if (sum - 100.0).abs() > 1e-9 {
+            return Err(SplitError::PercentagesDontAddUp(sum));
+        }
+        let weights = shares
+            .iter()
+            .map(|(payer, percent)| (payer.to_string(), *percent))
+            .collect();
+        allocate(self.total, weights)
+    }
+
+    // `payers[i]` pays for line `i` of the receipt.
+    pub fn split_by_lines(&self, payers: &[&str]) -> Result<Vec<PayerShare>, SplitError> {
+        if payers.len() != self.lines.len() {
+            return Err(SplitError::LineCountMismatch {
+                lines: self.lines.len(),
+                payers: payers.len(),
+            });
+        }
+        let mut line = 0;
+        self.split_by(|_| {
+            line += 1;
+            payers[line - 1].to_string()
+        })
+    }
+
+    // The most general split: the closure decides who pays for each line.
+    // Payers come back in the order they were first named.
+    pub fn split_by<F>(&self, mut payer_of: F) -> Result<Vec<PayerShare>, SplitError>
+    where
+        F: FnMut(&ReceiptLine) -> String,
+    {
+        let mut weights: Vec<(String, f64)> = Vec::new();
+
+        for line in &self.lines {
+            let payer = payer_of(line);
+            let amount = line.net(line.quantity) + line.tax(line.quantity);
+            match weights.iter_mut().find(|(name, _)| *name == payer) {
+                Some((_, weight)) => *weight += amount,
+                None => weights.push((payer, amount)),
+            }
+        }
+
+        allocate(self.total, weights)
+    }
+}
+
+impl fmt::Display for PayerShare {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:<12} {:>8.2}", self.payer, self.amount)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::receipt::Receipt;
+
+    fn shares(split: Result<Vec<PayerShare>, SplitError>) -> Vec<(String, f64)> {
+        split
+            .unwrap()
+            .into_iter()
+            .map(|share| (share.payer, share.amount))
+            .collect()
+    }
+
+    fn receipt(prices: &[f64], shipping_cost: f64) -> Receipt {
+        let lines: Vec<ReceiptLine> = prices
+            .iter()
+            .map(|&price| ReceiptLine {
+                name: "ITEM".to_string(),
+                price,
+                list_price: price,
+                price_cut: None,
+                multi_buy: None,
+                tax_rate: 0.0,
+                quantity: 1,
+                returned: 0,
+            })
+            .collect();
+        let goods: f64 = prices.iter().sum();
+        Receipt {
+            lines,
+            shipping: None,
+            tax: 0.0,
+            total: goods + shipping_cost,
+            payments: Vec::new(),
+            amount_due: 0.0,
+            issued_at: 0,
+            points: 0,
+            points_per_unit: 0,
+        }
+    }
+
+    #[test]
+    fn leftover_cents_go_to_the_largest_fractions_earliest_first() {
+        let weights = |values: &[f64]| -> Vec<(String, f64)> {
+            ["ana", "ben", "cleo"]
+                .iter()
+                .zip(values)
+                .map(|(payer, weight)| (payer.to_string(), *weight))
+                .collect()
+        };
+
+        // 33.333... each: the one leftover cent goes to the first payer.
+        assert_eq!(
+            shares(allocate(1.00, weights(&[1.0, 1.0, 1.0]))),
+            [
+                ("ana".to_string(), 0.34),
+                ("ben".to_string(), 0.33),
+                ("cleo".to_string(), 0.33)
+            ]
+        );
+        // 1.666..., 3.333... and 5 cents: ana's fraction is the largest.
+        assert_eq!(
+            shares(allocate(0.10, weights(&[1.0, 2.0, 3.0]))),
+            [
+                ("ana".to_string(), 0.02),
+                ("ben".to_string(), 0.03),
+                ("cleo".to_string(), 0.05)
+            ]
+        );
+    }
+
+    #[test]
+    fn free_lines_with_shipping_are_split_equally() {
+        let receipt = receipt(&[0.0, 0.0], 5.00);
+        assert_eq!(
+            shares(receipt.split_by_lines(&["ana", "ben"])),
+            [("ana".to_string(), 2.50), ("ben".to_string(), 2.50)]
+        );
+        assert_eq!(receipt.split_evenly(&[]).unwrap_err(), SplitError::NoPayers);
+    }
+
+    #[test]
+    fn shipping_follows_what_each_payer_bought() {
+        let receipt = receipt(&[3.00, 1.00], 2.00);
+        assert_eq!(
+            shares(receipt.split_by_lines(&["ana", "ben"])),
+            [("ana".to_string(), 4.50), ("ben".to_string(), 1.50)]
+        );
+    }
+
+    #[test]
+    fn percentages_must_be_valid_and_add_up() {
+        let receipt = receipt(&[10.00], 0.0);
+        assert!(matches!(
+            receipt.split_by_percentage(&[("ana", f64::NAN), ("ben", 100.0)]),
+            Err(SplitError::InvalidShare { payer, percent }) if payer == "ana" && percent.is_nan()
+        ));
+        assert!(matches!(
+            receipt.split_by_percentage(&[("ana", 150.0), ("ben", -50.0)]),
+            Err(SplitError::InvalidShare { .. })
+        ));
+        assert_eq!(
+            receipt
+                .split_by_percentage(&[("ana", 50.0), ("ben", 30.0)])
+                .unwrap_err(),
+            SplitError::PercentagesDontAddUp(80.0)
+        );
+    }
+}
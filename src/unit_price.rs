@@ -0,0 +1,170 @@
+// Unit prices ("3.20 per litre") so differently sized packs can be compared.
+// Every pack size is normalised to a metric base first (grams, millilitres
+// or a plain count), so a 4 pint bottle and a 1 litre carton compare
+// directly.
+
+use std::fmt;
+use std::str::FromStr;
+
+use crate::ShoppingCart;
+use crate::catalog::Catalog;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Unit {
+    Gram,
+    Kilogram,
+    Ounce,
+    Pound,
+    Millilitre,
+    Litre,
+    FluidOunce,
+    Pint,
+    Each,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PackSize {
+    pub amount: f64,
+    pub unit: Unit,
+}
+
+// What a pack size measures once it's normalised.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Measure {
+    Mass,
+    Volume,
+    Count,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct UnitPrice {
+    pub price: f64,
+    pub measure: Measure,
+}
+
+#[derive(Debug)]
+pub struct CheaperAlternative {
+    pub line: String,
+    pub line_price: UnitPrice,
+    pub sku: String,
+    pub name: String,
+    pub unit_price: UnitPrice,
+    pub saving_percent: f64,
+}
+
+impl Unit {
+    // The metric base unit it measures in, and how many of them one of
+    // this unit is. Imperial units use the UK pint and fluid ounce.
+    fn to_base(self) -> (Measure, f64) {
+        match self {
+            Unit::Gram => (Measure::Mass, 1.0),
+            Unit::Kilogram => (Measure::Mass, 1000.0),
+            Unit::Ounce => (Measure::Mass, 28.349_523_125),
+            Unit::Pound => (Measure::Mass, 453.592_37),
+            Unit::Millilitre => (Measure::Volume, 1.0),
+            Unit::Litre => (Measure::Volume, 1000.0),
+            Unit::FluidOunce => (Measure::Volume, 28.413_062_5),
+            Unit::Pint => (Measure::Volume, 568.261_25),
+            Unit::Each => (Measure::Count, 1.0),
+        }
+    }
+}
+
+// Parses the usual abbreviations ("g", "kg", "oz", "lb", "ml", "l",
+// "fl oz", "pt", "each") as well as the full names, in any case.
+impl FromStr for Unit {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Unit, String> {
+        match text.trim().to_lowercase().as_str() {
+            "g" | "gram" | "grams" => Ok(Unit::Gram),
+            "kg" | "kilogram" | "kilograms" => Ok(Unit::Kilogram),
+            "oz" | "ounce" | "ounces" => Ok(Unit::Ounce),
+            "lb" | "lbs" | "pound" | "pounds" => Ok(Unit::Pound),
+            "ml" | "millilitre" | "millilitres" => Ok(Unit::Millilitre),
+            "l" | "litre" | "litres" => Ok(Unit::Litre),
+            "fl oz" | "floz" | "fluid ounce" | "fluid ounces" => Ok(Unit::FluidOunce),
+            "pt" | "pint" | "pints" => Ok(Unit::Pint),
+            "each" | "ea" | "pcs" => Ok(Unit::Each),
+            other => Err(format!("unknown unit `{}`", other)),
+        }
+    }
+}
+
+impl PackSize {
+    pub fn new(amount: f64, unit: Unit) -> PackSize {
+        PackSize { amount, unit }
+    }
+
+    // What the pack measures, and how much of it there is in grams,
+    // millilitres or items.
+    pub fn base(&self) -> (Measure, f64) {
+        let (measure, factor) = self.unit.to_base();
+        (measure, self.amount * factor)
+    }
+
+    // Price per 100 g, per litre, or per item.
+    pub fn unit_price(&self, price: f64) -> UnitPrice {
+        let (measure, base_amount) = self.base();
+        let per = match measure {
+            Measure::Mass => 100.0,
+            Measure::Volume => 1000.0,
+            Measure::Count => 1.0,
+        };
+        UnitPrice {
+            price: price / base_amount * per,
+            measure,
+        }
+    }
+}
+
+impl fmt::Display for UnitPrice {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let per = match self.measure {
+            Measure::Mass => "per 100 g",
+            Measure::Volume => "per litre",
+            Measure::Count => "each",
+        };
+        write!(f, "{:.2} {}", self.price, per)
+    }
+}
+
+impl ShoppingCart {
+    // For every cart line with a pack size and a category, the catalog
+    // product in the same category and measure with the lowest unit price,
+    // if it's cheaper than the line.
+    pub fn cheaper_alternatives(&self, catalog: &Catalog) -> Vec<CheaperAlternative> {
+        let mut report = Vec::new();
+
+        for item in &self.item {
+            let (Some(pack), Some(category)) = (item.pack, &item.category) else {
+                continue;
+            };
+            let line_price = pack.unit_price(item.price);
+
+            let cheapest = catalog
+                .entries()
+                .iter()
+                .filter(|entry| entry.item.category.as_ref() == Some(category))
+                .filter_map(|entry| {
+                    let unit_price = entry.item.pack?.unit_price(entry.item.price);
+                    (unit_price.measure == line_price.measure).then_some((entry, unit_price))
+                })
+                .min_by(|a, b| a.1.price.total_cmp(&b.1.price));
+
+            if let Some((entry, unit_price)) = cheapest
+                && unit_price.price < line_price.price
+            {
+                report.push(CheaperAlternative {
+                    line: item.name.clone(),
+                    line_price,
+                    sku: entry.sku.clone(),
+                    name: entry.item.name.clone(),
+                    unit_price,
+                    saving_percent: (1.0 - unit_price.price / line_price.price) * 100.0,
+                });
+            }
+        }
+        report
+    }
+}
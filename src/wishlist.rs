@@ -0,0 +1,111 @@
+// Save-for-later and wishlists.
+// Items move between the cart and a wishlist by value: the SupermarketItem
+// itself is taken out of one Vec and pushed onto the other, never cloned.
+// Each saved item remembers what it cost when it was saved, so a drop in
+// price can be spotted later.
+
+use crate::error::CartError;
+use crate::{ShoppingCart, SupermarketItem};
+
+#[derive(Debug)]
+pub struct SavedItem {
+    pub item: SupermarketItem,
+    pub saved_price: f64,
+}
+
+#[derive(Debug)]
+pub struct Wishlist {
+    pub name: String,
+    pub items: Vec<SavedItem>,
+}
+
+#[derive(Debug)]
+pub struct Wishlists {
+    pub customer_id: String,
+    lists: Vec<Wishlist>,
+}
+
+#[derive(Debug)]
+pub struct PriceDrop {
+    pub list: String,
+    pub name: String,
+    pub saved_price: f64,
+    pub current_price: f64,
+}
+
+impl Wishlists {
+    pub fn new(customer_id: &str) -> Wishlists {
+        Wishlists {
+            customer_id: customer_id.to_string(),
+            lists: Vec::new(),
+        }
+    }
+
+    // The named list, created empty the first time it's asked for.
+    pub fn list(&mut self, name: &str) -> &mut Wishlist {
+        let index = match self.lists.iter().position(|list| list.name == name) {
+            Some(index) => index,
+            None => {
+                self.lists.push(Wishlist {
+                    name: name.to_string(),
+                    items: Vec::new(),
+                });
+                self.lists.len() - 1
+            }
+        };
+        &mut self.lists[index]
+    }
+
+    // Every saved item whose current price is lower than when it was saved.
+    // `current_price` looks a product up by its normalised name, e.g. in the
+    // catalog or the price history; products it doesn't know are skipped.
+    pub fn price_drops<F>(&self, current_price: F) -> Vec<PriceDrop>
+    where
+        F: Fn(&str) -> Option<f64>,
+    {
+        let mut drops = Vec::new();
+        for list in &self.lists {
+            for saved in &list.items {
+                if let Some(price) = current_price(&saved.item.normalized_name())
+                    && price < saved.saved_price
+                {
+                    drops.push(PriceDrop {
+                        list: list.name.clone(),
+                        name: saved.item.name.clone(),
+                        saved_price: saved.saved_price,
+                        current_price: price,
+                    });
+                }
+            }
+        }
+        drops
+    }
+}
+
+impl ShoppingCart {
+    pub fn move_to_wishlist(
+        &mut self,
+        line: usize,
+        wishlist: &mut Wishlist,
+    ) -> Result<(), CartError> {
+        if line >= self.item.len() {
+            return Err(CartError::NoSuchLine(line));
+        }
+        let item = self.item.remove(line);
+        wishlist.items.push(SavedItem {
+            saved_price: item.price,
+            item,
+        });
+        Ok(())
+    }
+
+    // Moves the wishlist entry at `index` back to the end of the cart.
+    pub fn move_to_cart(&mut self, index: usize, wishlist: &mut Wishlist) -> Result<(), CartError> {
+        if index >= wishlist.items.len() {
+            return Err(CartError::NoSuchLine(index));
+        }
+        let saved = wishlist.items.remove(index);
+        self.item.push(saved.item);
+        Ok(())
+    }
+}